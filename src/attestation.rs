@@ -0,0 +1,430 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashSet};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::encoding::Encoder;
+use crate::error::{Error, Result};
+use crate::registry::{Registry, Role};
+use crate::voter::{base64_key, base64_signature, SignedVote};
+
+pub const ATTESTATION_DOMAIN: &[u8] = b"chronovote/attestation/v1";
+
+/// Hash of the signed vote's binary encoding; this is what a validator countersigns.
+pub fn vote_hash(vote: &SignedVote) -> Result<[u8; 32]> {
+    Ok(Sha256::digest(vote.encode()?).into())
+}
+
+/// A validator's signed receipt: "I saw this vote at `received_at`".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attestation {
+    pub validator_id: String,
+    pub received_at: DateTime<Utc>,
+    #[serde(with = "base64_signature")]
+    pub signature: Signature,
+    #[serde(with = "base64_key")]
+    pub public_key: VerifyingKey,
+}
+
+impl Attestation {
+    /// Countersigns `vote` as `validator_id`.
+    pub fn sign(vote: &SignedVote, validator_id: &str, key: &SigningKey, received_at: DateTime<Utc>) -> Result<Self> {
+        let message = attestation_message(validator_id, vote, &received_at)?;
+        Ok(Attestation {
+            validator_id: validator_id.to_string(),
+            received_at,
+            signature: key.sign(&message),
+            public_key: key.verifying_key(),
+        })
+    }
+
+    /// True when this receipt was signed over exactly `vote`.
+    pub fn verifies(&self, vote: &SignedVote) -> bool {
+        attestation_message(&self.validator_id, vote, &self.received_at)
+            .is_ok_and(|message| self.public_key.verify(&message, &self.signature).is_ok())
+    }
+}
+
+fn attestation_message(validator_id: &str, vote: &SignedVote, received_at: &DateTime<Utc>) -> Result<Vec<u8>> {
+    Encoder::new(ATTESTATION_DOMAIN)
+        .str(validator_id)
+        .raw(&vote_hash(vote)?)
+        .timestamp(received_at)
+        .finish()
+}
+
+/// A signed vote countersigned by the validator it names.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttestedVote {
+    pub vote: SignedVote,
+    pub attestation: Attestation,
+}
+
+impl AttestedVote {
+    pub fn attest(vote: SignedVote, validator_key: &SigningKey, received_at: DateTime<Utc>) -> Result<Self> {
+        let attestation = Attestation::sign(&vote, &vote.vote.validator_id, validator_key, received_at)?;
+        Ok(AttestedVote { vote, attestation })
+    }
+
+    /// True when the attestation comes from the vote's own validator and
+    /// covers exactly this vote. Does not check the voter's signature.
+    pub fn attestation_verifies(&self) -> bool {
+        self.attestation.validator_id == self.vote.vote.validator_id && self.attestation.verifies(&self.vote)
+    }
+
+    /// Checks both the voter's signature and the validator's countersignature.
+    pub fn ensure_valid(&self) -> Result<()> {
+        self.vote.ensure_valid()?;
+        if self.attestation_verifies() {
+            Ok(())
+        } else {
+            Err(Error::InvalidAttestation {
+                voter_id: self.vote.vote.voter_id.clone(),
+                validator_id: self.attestation.validator_id.clone(),
+            })
+        }
+    }
+
+    pub fn attested_time(&self) -> DateTime<Utc> {
+        self.attestation.received_at
+    }
+
+    /// How far the voter's claimed time is from the attested one, in either direction.
+    pub fn drift(&self) -> Duration {
+        (self.attestation.received_at - self.vote.vote.vote_time).abs()
+    }
+
+    pub fn check_drift(&self, tolerance: Duration) -> Result<()> {
+        if self.drift() <= tolerance {
+            Ok(())
+        } else {
+            Err(Error::TimestampDrift {
+                voter_id: self.vote.vote.voter_id.clone(),
+                claimed: self.vote.vote.vote_time,
+                attested: self.attestation.received_at,
+            })
+        }
+    }
+}
+
+/// How many receipts a vote needs and how far one may sit from the median
+/// before it is treated as an outlier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuorumPolicy {
+    pub min_attestations: usize,
+    pub outlier_tolerance: Duration,
+}
+
+impl Default for QuorumPolicy {
+    fn default() -> Self {
+        QuorumPolicy {
+            min_attestations: 3,
+            outlier_tolerance: Duration::seconds(5),
+        }
+    }
+}
+
+/// A signed vote with receipts from any number of validators.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuorumAttestedVote {
+    pub vote: SignedVote,
+    pub attestations: Vec<Attestation>,
+}
+
+/// The effective time of a quorum-attested vote and how each validator's
+/// receipt compared to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuorumTime {
+    pub time: DateTime<Utc>,
+    /// Receipt time minus `time`, for every validator with a valid receipt.
+    pub offsets: Vec<(String, Duration)>,
+    /// Validators left out of the median for being beyond the outlier tolerance.
+    pub outliers: Vec<String>,
+}
+
+fn median(times: &mut [DateTime<Utc>]) -> DateTime<Utc> {
+    times.sort();
+    let mid = times.len() / 2;
+    if times.len() % 2 == 1 {
+        times[mid]
+    } else {
+        times[mid - 1] + (times[mid] - times[mid - 1]) / 2
+    }
+}
+
+impl QuorumAttestedVote {
+    pub fn new(vote: SignedVote) -> Self {
+        QuorumAttestedVote { vote, attestations: Vec::new() }
+    }
+
+    pub fn add(&mut self, attestation: Attestation) {
+        self.attestations.push(attestation);
+    }
+
+    /// Receipts that verify against this vote under the key `registry` held for
+    /// their validator when they were issued, one per validator (the first
+    /// wins). Receipts from unknown ids or under other keys are skipped.
+    pub fn valid_attestations(&self, registry: &Registry) -> Vec<&Attestation> {
+        let mut seen = HashSet::new();
+        self.attestations
+            .iter()
+            .filter(|a| {
+                registry.key_at(Role::Validator, &a.validator_id, a.received_at) == Some(&a.public_key)
+                    && a.verifies(&self.vote)
+            })
+            .filter(|a| seen.insert(a.validator_id.as_str()))
+            .collect()
+    }
+
+    /// Median of the valid receipts, recomputed after dropping outliers, so a
+    /// minority of skewed clocks cannot move the result. Fails when fewer than
+    /// `min_attestations` receipts remain.
+    pub fn quorum_time(&self, registry: &Registry, policy: &QuorumPolicy) -> Result<QuorumTime> {
+        let valid = self.valid_attestations(registry);
+        let no_quorum = |attestations| Error::NoTimestampQuorum {
+            voter_id: self.vote.vote.voter_id.clone(),
+            attestations,
+            required: policy.min_attestations,
+        };
+        if valid.is_empty() || valid.len() < policy.min_attestations {
+            return Err(no_quorum(valid.len()));
+        }
+
+        let first_pass = median(&mut valid.iter().map(|a| a.received_at).collect::<Vec<_>>());
+        let (inliers, outliers): (Vec<&Attestation>, Vec<&Attestation>) = valid
+            .iter()
+            .copied()
+            .partition(|a| (a.received_at - first_pass).abs() <= policy.outlier_tolerance);
+        if inliers.len() < policy.min_attestations {
+            return Err(no_quorum(inliers.len()));
+        }
+
+        let time = median(&mut inliers.iter().map(|a| a.received_at).collect::<Vec<_>>());
+        Ok(QuorumTime {
+            time,
+            offsets: valid
+                .iter()
+                .map(|a| (a.validator_id.clone(), a.received_at - time))
+                .collect(),
+            outliers: outliers.iter().map(|a| a.validator_id.clone()).collect(),
+        })
+    }
+}
+
+/// Running per-validator record of how far receipts sat from the quorum time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DriftStats {
+    pub samples: u32,
+    pub outliers: u32,
+    pub total_offset: Duration,
+}
+
+impl DriftStats {
+    pub fn mean_offset(&self) -> Duration {
+        if self.samples == 0 {
+            Duration::zero()
+        } else {
+            self.total_offset / self.samples as i32
+        }
+    }
+}
+
+/// Collects [`QuorumTime`] offsets over many votes to spot validators whose
+/// clocks are consistently off rather than occasionally late.
+#[derive(Debug, Clone, Default)]
+pub struct DriftMonitor {
+    pub stats: BTreeMap<String, DriftStats>,
+}
+
+impl DriftMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, quorum: &QuorumTime) {
+        for (validator_id, offset) in &quorum.offsets {
+            let stats = self.stats.entry(validator_id.clone()).or_default();
+            stats.samples += 1;
+            stats.total_offset += *offset;
+            if quorum.outliers.contains(validator_id) {
+                stats.outliers += 1;
+            }
+        }
+    }
+
+    /// Validators with at least `min_samples` receipts whose mean offset is
+    /// beyond `tolerance` in either direction.
+    pub fn drifting(&self, min_samples: u32, tolerance: Duration) -> Vec<(&str, &DriftStats)> {
+        self.stats
+            .iter()
+            .filter(|(_, s)| s.samples >= min_samples && s.mean_offset().abs() > tolerance)
+            .map(|(id, s)| (id.as_str(), s))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voter::{Choice, Vote};
+    use rand::rngs::OsRng;
+    use rust_decimal_macros::dec;
+
+    fn signed_vote(vote_time: DateTime<Utc>) -> SignedVote {
+        Vote {
+            voter_id: "Alice".into(),
+            validator_id: "Val1".into(),
+            vote_time,
+            vote_weight: dec!(1),
+            choice: Choice::Yes,
+            proposal_id: "p1".into(),
+            nonce: 1,
+        }
+        .sign(&SigningKey::generate(&mut OsRng))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_attestation_round_trip() {
+        let now = Utc::now();
+        let attested = AttestedVote::attest(signed_vote(now), &SigningKey::generate(&mut OsRng), now).unwrap();
+        assert!(attested.ensure_valid().is_ok());
+
+        let json = serde_json::to_string(&attested).unwrap();
+        let back: AttestedVote = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, attested);
+        assert!(back.ensure_valid().is_ok());
+    }
+
+    #[test]
+    fn test_attestation_is_bound_to_vote_and_time() {
+        let now = Utc::now();
+        let validator = SigningKey::generate(&mut OsRng);
+        let attested = AttestedVote::attest(signed_vote(now), &validator, now).unwrap();
+
+        let mut moved = attested.clone();
+        moved.attestation.received_at = now - Duration::seconds(60);
+        assert!(matches!(moved.ensure_valid(), Err(Error::InvalidAttestation { .. })));
+
+        let mut swapped = attested.clone();
+        swapped.vote = signed_vote(now);
+        assert!(matches!(swapped.ensure_valid(), Err(Error::InvalidAttestation { .. })));
+
+        let mut other_validator = attested;
+        other_validator.attestation.validator_id = "Val2".into();
+        assert!(!other_validator.attestation_verifies());
+    }
+
+    #[test]
+    fn test_drift_tolerance() {
+        let now = Utc::now();
+        let validator = SigningKey::generate(&mut OsRng);
+        let late = AttestedVote::attest(signed_vote(now - Duration::seconds(10)), &validator, now).unwrap();
+        assert_eq!(late.drift(), Duration::seconds(10));
+        assert!(late.check_drift(Duration::seconds(10)).is_ok());
+        assert!(matches!(
+            late.check_drift(Duration::seconds(5)),
+            Err(Error::TimestampDrift { .. })
+        ));
+
+        let early = AttestedVote::attest(signed_vote(now + Duration::seconds(10)), &validator, now).unwrap();
+        assert_eq!(early.drift(), Duration::seconds(10));
+    }
+
+    /// A vote with one receipt per offset from validators Val1, Val2, ...,
+    /// all registered an hour before `base`.
+    fn quorum_vote(base: DateTime<Utc>, offsets: &[i64]) -> (QuorumAttestedVote, Vec<SigningKey>, Registry) {
+        let keys: Vec<SigningKey> = offsets.iter().map(|_| SigningKey::generate(&mut OsRng)).collect();
+        let mut registry = Registry::new();
+        let mut vote = QuorumAttestedVote::new(signed_vote(base));
+        for (i, (offset, key)) in offsets.iter().zip(&keys).enumerate() {
+            let id = format!("Val{}", i + 1);
+            registry.register(Role::Validator, &id, key.verifying_key(), base - Duration::hours(1)).unwrap();
+            let receipt = Attestation::sign(&vote.vote, &id, key, base + Duration::seconds(*offset));
+            vote.add(receipt.unwrap());
+        }
+        (vote, keys, registry)
+    }
+
+    #[test]
+    fn test_quorum_median_ignores_backdating_validator() {
+        let base = Utc::now();
+        // Val4 claims the vote arrived ten minutes earlier
+        let (vote, _, registry) = quorum_vote(base, &[1, 2, 3, -600]);
+        let quorum = vote.quorum_time(&registry, &QuorumPolicy::default()).unwrap();
+        assert_eq!(quorum.time, base + Duration::seconds(2));
+        assert_eq!(quorum.outliers, vec!["Val4".to_string()]);
+        assert_eq!(quorum.offsets.len(), 4);
+    }
+
+    #[test]
+    fn test_quorum_requires_enough_valid_receipts() {
+        let base = Utc::now();
+        let policy = QuorumPolicy::default();
+        let (mut vote, keys, mut registry) = quorum_vote(base, &[0, 1]);
+        assert_eq!(
+            vote.quorum_time(&registry, &policy),
+            Err(Error::NoTimestampQuorum { voter_id: "Alice".into(), attestations: 2, required: 3 })
+        );
+
+        // a second receipt from the same validator does not count twice
+        let repeat = Attestation::sign(&vote.vote, "Val1", &keys[0], base).unwrap();
+        vote.add(repeat);
+        assert!(vote.quorum_time(&registry, &policy).is_err());
+
+        // nor does a receipt signed over another vote
+        let val3 = SigningKey::generate(&mut OsRng);
+        registry.register(Role::Validator, "Val3", val3.verifying_key(), base - Duration::hours(1)).unwrap();
+        vote.add(Attestation::sign(&signed_vote(base), "Val3", &val3, base).unwrap());
+        assert!(vote.quorum_time(&registry, &policy).is_err());
+
+        vote.add(Attestation::sign(&vote.vote, "Val3", &val3, base).unwrap());
+        assert_eq!(vote.quorum_time(&registry, &policy).unwrap().time, base);
+
+        // outliers do not count towards the quorum
+        let (split, _, registry) = quorum_vote(base, &[0, 1, 60, 61]);
+        assert!(matches!(split.quorum_time(&registry, &policy), Err(Error::NoTimestampQuorum { .. })));
+    }
+
+    #[test]
+    fn test_self_signed_receipts_do_not_reach_quorum() {
+        let base = Utc::now();
+        let policy = QuorumPolicy::default();
+        let (mut vote, _, registry) = quorum_vote(base, &[0]);
+
+        // the voter mints receipts under made-up ids, and one under Val1's name
+        let forger = SigningKey::generate(&mut OsRng);
+        for id in ["Val1", "Sybil1", "Sybil2", "Sybil3"] {
+            let receipt = Attestation::sign(&vote.vote, id, &forger, base - Duration::minutes(10));
+            vote.attestations.insert(0, receipt.unwrap());
+        }
+        assert_eq!(
+            vote.quorum_time(&registry, &policy),
+            Err(Error::NoTimestampQuorum { voter_id: "Alice".into(), attestations: 1, required: 3 })
+        );
+        // the forged Val1 receipt came first but does not shadow the real one
+        assert_eq!(vote.valid_attestations(&registry)[0].received_at, base);
+    }
+
+    #[test]
+    fn test_drift_monitor_flags_consistent_skew() {
+        let base = Utc::now();
+        let mut monitor = DriftMonitor::new();
+        for _ in 0..5 {
+            let (vote, _, registry) = quorum_vote(base, &[0, 1, -1, 5]);
+            monitor.record(&vote.quorum_time(&registry, &QuorumPolicy::default()).unwrap());
+        }
+        let (vote, _, registry) = quorum_vote(base, &[0, 12, 1, 0]);
+        monitor.record(&vote.quorum_time(&registry, &QuorumPolicy::default()).unwrap());
+
+        let drifting = monitor.drifting(5, Duration::seconds(3));
+        assert_eq!(drifting.len(), 1);
+        assert_eq!(drifting[0].0, "Val4");
+        assert_eq!(drifting[0].1.samples, 6);
+
+        // Val2 was an outlier once but is not consistently off
+        assert_eq!(monitor.stats["Val2"].outliers, 1);
+        assert!(monitor.drifting(5, Duration::seconds(6)).is_empty());
+    }
+}
@@ -1,9 +1,98 @@
 use chrono::{DateTime,Utc};
-use rand::seq::SliceRandom;
-use rand::thread_rng;
 use sha2::{Sha256,Digest};
 use serde::{Serialize,Deserialize};
 
+use crate::clock::{system_clock,Clock};
+use crate::encoding::{Decoder,Encoder};
+use crate::error::{Error,Result};
+use crate::payload::BlockPayload;
+use crate::merkle::{Hash,MerkleProof,MerkleTree};
+use crate::voter::SignedVote;
+use std::sync::Arc;
+
+pub const BLOCK_HEADER_DOMAIN:&[u8]=b"chronovote/block-header/v1";
+
+// What a block's hash commits to. The payload is only included through its digest
+// so headers stay small and fixed in shape.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct BlockHeader{
+    pub index:u64,
+    pub timestamp:DateTime<Utc>,
+    pub prev_hash:String,
+    pub payload_hash:[u8;32],
+    pub votes_root:Option<Hash>,
+}
+
+impl BlockHeader{
+    //canonical v1 encoding (see the encoding module)
+    pub fn encode(&self)->Result<Vec<u8>>{
+        let mut enc=Encoder::new(BLOCK_HEADER_DOMAIN);
+        enc.u64(self.index)
+            .timestamp(&self.timestamp)
+            .str(&self.prev_hash)
+            .raw(&self.payload_hash);
+        match &self.votes_root{
+            Some(root)=>enc.u8(1).raw(root),
+            None=>enc.u8(0),
+        };
+        enc.finish()
+    }
+
+    pub fn decode(bytes:&[u8])->Result<BlockHeader>{
+        let mut dec=Decoder::new(bytes,BLOCK_HEADER_DOMAIN)?;
+        let index=dec.u64()?;
+        let timestamp=dec.timestamp()?;
+        let prev_hash=dec.str()?;
+        let payload_hash=dec.raw::<32>()?;
+        let votes_root=match dec.u8()?{
+            0=>None,
+            1=>Some(dec.raw::<32>()?),
+            tag=>return Err(Error::InvalidEncoding(format!("unknown votes root tag {}",tag))),
+        };
+        dec.finish()?;
+        Ok(BlockHeader{index,timestamp,prev_hash,payload_hash,votes_root})
+    }
+
+    pub fn hash(&self)->Result<String>{
+        Ok(format!("{:x}",Sha256::digest(self.encode()?)))
+    }
+}
+
+pub fn payload_digest(data:&[u8])->[u8;32]{
+    Sha256::digest(data).into()
+}
+
+fn to_hex(bytes:&[u8])->String{
+    bytes.iter().map(|b| format!("{:02x}",b)).collect()
+}
+
+//only the form to_hex writes: exactly 64 lowercase hex digits, so each hash has one spelling
+fn from_hex(s:&str)->Option<Hash>{
+    let digits=s.as_bytes();
+    if digits.len()!=64{
+        return None;
+    }
+    let nibble=|c:u8| match c{
+        b'0'..=b'9'=>Some(c-b'0'),
+        b'a'..=b'f'=>Some(c-b'a'+10),
+        _=>None,
+    };
+    let mut out=[0u8;32];
+    for (byte,pair) in out.iter_mut().zip(digits.chunks_exact(2)){
+        *byte=nibble(pair[0])?<<4|nibble(pair[1])?;
+    }
+    Some(out)
+}
+
+// Why a block failed validation.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ChainFault{
+    IndexMismatch,    //block.index differs from its position
+    HashMismatch,     //stored hash differs from the recomputed header hash
+    PrevHashMismatch, //prev_hash doesn't match the previous block's hash
+    MalformedVotesRoot, //votes_root is set but isn't 64 lowercase hex characters
+}
+
 #[derive(Debug,Clone,Serialize,Deserialize)]
 pub struct Block{
     pub index:usize,
@@ -11,61 +100,149 @@ pub struct Block{
     pub data:String,
     pub prev_hash:String,
     pub hash:String,
+    #[serde(default)]
+    pub votes_root:Option<String>, //hex merkle root over the block's signed votes
 }
 
 impl Block{
     pub fn new(index:usize,timestamp:DateTime<Utc>,data:String,prev_hash:String)->Self{
+        Self::build(index,timestamp,data,prev_hash,None)
+    }
+
+    pub fn with_votes(index:usize,timestamp:DateTime<Utc>,data:String,prev_hash:String,votes:&[SignedVote])->Result<Self>{
+        let root=MerkleTree::from_votes(votes)?.root();
+        Ok(Self::build(index,timestamp,data,prev_hash,Some(to_hex(&root))))
+    }
+
+    fn build(index:usize,timestamp:DateTime<Utc>,data:String,prev_hash:String,votes_root:Option<String>)->Self{
         let mut block=Block{
             index,
             timestamp,
             data,
             prev_hash,
             hash:String::new(),
+            votes_root,
         };
-        block.hash=block.calculate_hash();
+        //a header that can't be encoded gets no hash, so the block never validates
+        block.hash=block.calculate_hash().unwrap_or_default();
         block
     }
-    pub fn calculate_hash(&self)->String{
-        let mut hasher=Sha256::new();
-        hasher.update(self.index.to_string());
-        hasher.update(self.timestamp.to_rfc3339());
-        hasher.update(&self.data);
-        hasher.update(&self.hash);
-        format!("{:x}",hasher.finalize())
+
+    pub fn payload(&self)->Result<BlockPayload>{
+        BlockPayload::decode(&self.data)
+    }
+
+    //None when absent or malformed; validate reports the malformed case
+    pub fn votes_root(&self)->Option<Hash>{
+        from_hex(self.votes_root.as_deref()?)
+    }
+
+    //checks that the vote is one of the votes this block committed to
+    pub fn contains_vote(&self,vote:&SignedVote,proof:&MerkleProof)->bool{
+        match self.votes_root(){
+            Some(root)=>vote.encode().is_ok_and(|leaf| proof.verify(&root,&leaf)),
+            None=>false,
+        }
+    }
+    pub fn header(&self)->BlockHeader{
+        BlockHeader{
+            index:self.index as u64,
+            timestamp:self.timestamp,
+            prev_hash:self.prev_hash.clone(),
+            payload_hash:payload_digest(self.data.as_bytes()),
+            votes_root:self.votes_root(),
+        }
+    }
+
+    pub fn calculate_hash(&self)->Result<String>{
+        self.header().hash()
     }
 }
 
 pub struct Blockchain{
     pub blocks:Vec<Block>,
+    pub clock:Arc<dyn Clock>,
+}
+
+impl Default for Blockchain{
+    fn default()->Self{
+        Self::new()
+    }
 }
 
 impl Blockchain{
     pub fn new()->Self{
-        let now=Utc::now();
+        Self::with_clock(system_clock())
+    }
+
+    pub fn with_clock(clock:Arc<dyn Clock>)->Self{
+        let now=clock.now();
        //lets add a genesis block
         let genesis = Block::new(0, now, String::from("Genesis Block"), String::from("0")); 
         Blockchain{
                 blocks:vec![genesis],
+                clock,
         }
     }
+    //rebuilds a chain from stored blocks, rejecting it if it doesn't validate
+    pub fn from_blocks(blocks:Vec<Block>,clock:Arc<dyn Clock>)->Result<Self>{
+        let chain=Blockchain{blocks,clock};
+        chain.validate()?;
+        Ok(chain)
+    }
+
     pub fn add_blocks(&mut self,data:String){
         let last_block=self.blocks.last().unwrap();
         let block=Block::new(
                 self.blocks.len(),
-            Utc::now(),
+            self.clock.now(),
                        data,
             last_block.hash.clone(),
         );
         self.blocks.push(block)
     }
 
+    pub fn add_payload(&mut self,payload:&BlockPayload){
+        self.add_blocks(payload.encode())
+    }
+
+    //appends a block whose header commits to the merkle root of `votes`
+    pub fn add_vote_block(&mut self,data:String,votes:&[SignedVote])->Result<()>{
+        let last_block=self.blocks.last().unwrap();
+        let block=Block::with_votes(
+            self.blocks.len(),
+            self.clock.now(),
+            data,
+            last_block.hash.clone(),
+            votes,
+        )?;
+        self.blocks.push(block);
+        Ok(())
+    }
+
     pub fn is_valid(&self)->bool{
-        for i in 1..self.blocks.len(){
-            if self.blocks[i].prev_hash!=self.blocks[i-1].hash{
-                return false
+        self.validate().is_ok()
+    }
+
+    //recomputes every hash and reports the first block that doesn't check out
+    pub fn validate(&self)->Result<()>{
+        for (i,block) in self.blocks.iter().enumerate(){
+            let fault=if block.index!=i{
+                Some(ChainFault::IndexMismatch)
+            }else if block.votes_root.is_some()&&block.votes_root().is_none(){
+                Some(ChainFault::MalformedVotesRoot)
+            }else if block.calculate_hash().ok().as_ref()!=Some(&block.hash){
+                Some(ChainFault::HashMismatch)
+            }else if i>0&&block.prev_hash!=self.blocks[i-1].hash{
+                Some(ChainFault::PrevHashMismatch)
+            }else{
+                None
+            };
+            if let Some(fault)=fault{
+                return Err(Error::InvalidChain{index:i,fault});
             }
         }
-        true
+        Ok(())
     }
 }
 
@@ -117,7 +294,11 @@ mod tests {
         blockchain.blocks[1].data = "Tampered Data".to_string();
 
         // This should now be invalid because the hash chain is broken
-        assert!(blockchain.is_valid());
+        assert!(!blockchain.is_valid());
+        assert_eq!(
+            blockchain.validate(),
+            Err(Error::InvalidChain { index: 1, fault: ChainFault::HashMismatch })
+        );
     }
 
     #[test]
@@ -137,4 +318,202 @@ mod tests {
 
         assert_eq!(block1.hash, block2.hash);
     }
+
+    #[test]
+    fn test_block_timestamps_follow_clock() {
+        use crate::clock::SimulatedClock;
+        use chrono::Duration;
+
+        let start = Utc::now();
+        let clock = Arc::new(SimulatedClock::new(start));
+        let mut blockchain = Blockchain::with_clock(clock.clone());
+
+        clock.advance(Duration::seconds(30));
+        blockchain.add_blocks("Later".to_string());
+
+        assert_eq!(blockchain.blocks[0].timestamp, start);
+        assert_eq!(blockchain.blocks[1].timestamp, start + Duration::seconds(30));
+    }
+
+    #[test]
+    fn test_rehashed_tamper_breaks_next_link() {
+        let mut blockchain = Blockchain::new();
+        blockchain.add_blocks("Alpha".to_string());
+        blockchain.add_blocks("Beta".to_string());
+
+        // Rewriting a block and its hash still breaks the link from the block after it
+        blockchain.blocks[1].data = "Forged".to_string();
+        blockchain.blocks[1].hash = blockchain.blocks[1].calculate_hash().unwrap();
+
+        assert_eq!(
+            blockchain.validate(),
+            Err(Error::InvalidChain { index: 2, fault: ChainFault::PrevHashMismatch })
+        );
+    }
+
+    #[test]
+    fn test_index_tampering_detected() {
+        let mut blockchain = Blockchain::new();
+        blockchain.add_blocks("Alpha".to_string());
+        blockchain.blocks[1].index = 7;
+
+        assert_eq!(
+            blockchain.validate(),
+            Err(Error::InvalidChain { index: 1, fault: ChainFault::IndexMismatch })
+        );
+    }
+
+    #[test]
+    fn test_hash_commits_to_prev_hash() {
+        let timestamp = Utc::now();
+        let block1 = Block::new(1, timestamp, "Same".to_string(), "abc".to_string());
+        let block2 = Block::new(1, timestamp, "Same".to_string(), "abd".to_string());
+
+        assert_ne!(block1.hash, block2.hash);
+    }
+
+    #[test]
+    fn test_header_encoding_layout() {
+        use chrono::TimeZone;
+
+        let header = BlockHeader {
+            index: 1,
+            timestamp: Utc.timestamp_opt(1_700_000_000, 5).unwrap(),
+            prev_hash: "ab".to_string(),
+            payload_hash: payload_digest(b"data"),
+            votes_root: None,
+        };
+        let bytes = header.encode().unwrap();
+        let d = 4 + BLOCK_HEADER_DOMAIN.len();
+
+        assert_eq!(&bytes[..4], &(BLOCK_HEADER_DOMAIN.len() as u32).to_be_bytes());
+        assert_eq!(&bytes[4..d], BLOCK_HEADER_DOMAIN);
+        assert_eq!(&bytes[d..d + 8], &1u64.to_be_bytes());
+        assert_eq!(&bytes[d + 8..d + 16], &1_700_000_000i64.to_be_bytes());
+        assert_eq!(&bytes[d + 16..d + 20], &5u32.to_be_bytes());
+        assert_eq!(&bytes[d + 20..d + 24], &2u32.to_be_bytes());
+        assert_eq!(&bytes[d + 24..d + 26], b"ab");
+        assert_eq!(bytes[d + 58], 0);
+        assert_eq!(bytes.len(), d + 26 + 32 + 1);
+    }
+
+    #[test]
+    fn test_vote_block_inclusion_proof() {
+        use crate::merkle::prove_vote;
+        use crate::voter::{Choice, Vote};
+        use ed25519_dalek::SigningKey;
+        use rand::rngs::OsRng;
+        use rust_decimal_macros::dec;
+
+        let votes: Vec<SignedVote> = ["alice", "bob", "carol"]
+            .iter()
+            .map(|voter| {
+                Vote {
+                    voter_id: voter.to_string(),
+                    validator_id: "val".to_string(),
+                    vote_time: Utc::now(),
+                    vote_weight: dec!(1.0),
+                    choice: Choice::Yes,
+                    proposal_id: "p1".into(),
+                    nonce: 0,
+                }
+                .sign(&SigningKey::generate(&mut OsRng))
+                .unwrap()
+            })
+            .collect();
+
+        let mut blockchain = Blockchain::new();
+        blockchain.add_vote_block("votes".to_string(), &votes).unwrap();
+        let block = &blockchain.blocks[1];
+        assert!(block.votes_root.is_some());
+        assert!(blockchain.is_valid());
+
+        let proof = prove_vote(&votes, "bob").unwrap();
+        assert!(block.contains_vote(&votes[1], &proof));
+        assert!(!block.contains_vote(&votes[0], &proof));
+        assert!(!blockchain.blocks[0].contains_vote(&votes[1], &proof));
+        assert!(prove_vote(&votes, "mallory").is_none());
+
+        // the root is part of the header, so swapping it invalidates the block
+        blockchain.blocks[1].votes_root = Some("00".repeat(32));
+        assert_eq!(
+            blockchain.validate(),
+            Err(Error::InvalidChain { index: 1, fault: ChainFault::HashMismatch })
+        );
+
+        // garbage that doesn't parse is reported, not treated as "no root"
+        blockchain.blocks[1].votes_root = Some("zz".repeat(32));
+        assert_eq!(
+            blockchain.validate(),
+            Err(Error::InvalidChain { index: 1, fault: ChainFault::MalformedVotesRoot })
+        );
+        let mut rootless = blockchain.blocks[0].clone();
+        rootless.votes_root = Some("abc".to_string());
+        blockchain.blocks[0] = rootless;
+        assert_eq!(
+            blockchain.validate(),
+            Err(Error::InvalidChain { index: 0, fault: ChainFault::MalformedVotesRoot })
+        );
+    }
+
+    #[test]
+    fn test_from_hex_accepts_only_canonical_hex() {
+        let root = "0123456789abcdef".repeat(4);
+        let hash = from_hex(&root).unwrap();
+        assert_eq!(to_hex(&hash), root);
+
+        assert_eq!(from_hex(&root.to_uppercase()), None);
+        assert_eq!(from_hex(&"0123456789ABCDEF".repeat(4)), None);
+        assert_eq!(from_hex(&root[..62]), None);
+        assert_eq!(from_hex(&format!("{}00", root)), None);
+        assert_eq!(from_hex(&format!("+f{}", &root[2..])), None);
+        assert_eq!(from_hex(&format!("0x{}", &root[2..])), None);
+        assert_eq!(from_hex(&format!(" {}", &root[1..])), None);
+        assert_eq!(from_hex(&format!("é{}", &root[2..])), None);
+    }
+
+    #[test]
+    fn test_typed_payload_blocks() {
+        let mut blockchain = Blockchain::new();
+        let payload = BlockPayload::ParameterChange {
+            name: "grace_period_secs".to_string(),
+            value: serde_json::json!(120),
+        };
+        blockchain.add_payload(&payload);
+
+        assert_eq!(blockchain.blocks[1].payload().unwrap(), payload);
+        assert_eq!(
+            blockchain.blocks[0].payload().unwrap(),
+            BlockPayload::Legacy("Genesis Block".to_string())
+        );
+        assert!(blockchain.is_valid());
+    }
+
+    // Test vector for the v1 block header encoding and hash.
+    #[test]
+    fn test_canonical_header_vector() {
+        use chrono::TimeZone;
+
+        let header = BlockHeader {
+            index: 1,
+            timestamp: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+            prev_hash: "0".to_string(),
+            payload_hash: payload_digest(b"hello"),
+            votes_root: None,
+        };
+        let hex: String = header.encode().unwrap().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            hex,
+            concat!(
+                "0000001a6368726f6e6f766f74652f626c6f636b2d6865616465722f7631", // "chronovote/block-header/v1"
+                "0000000000000001",
+                "000000006553f100", "00000000",
+                "0000000130",
+                "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824", // sha256("hello")
+                "00",
+            )
+        );
+        assert_eq!(header.hash().unwrap(), "0fd04baed0a6209e80d9caeb4259f0d78ca9968838882ad479ea7405bd651f50");
+        assert_eq!(BlockHeader::decode(&header.encode().unwrap()).unwrap(), header);
+    }
 }
@@ -0,0 +1,500 @@
+use chrono::{DateTime,Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::{FromPrimitive,ToPrimitive};
+use rust_decimal_macros::dec;
+use serde::{Deserialize,Serialize};
+
+use crate::error::{Error,Result};
+
+//decimal places every decimal decay result is rounded to (banker's rounding);
+//exp is accurate far below this, so all nodes agree on every digit kept
+pub const DECAY_PRECISION:u32=18;
+
+const E:Decimal=dec!(2.7182818284590452353602874714);
+const E_INV:Decimal=dec!(0.3678794411714423215955237702);
+//e^x overflows Decimal above this and rounds to zero below its negation
+const EXP_LIMIT:i64=64;
+
+//serializes externally tagged, e.g. {"linear":0.01} or
+//{"floored":{"floor":0.25,"model":{"half_life":{"half_life_secs":600}}}}
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all="snake_case")]
+pub enum DecayModel{
+    Linear(f64),       //1% per minute
+    Exponential(f64), //0.1% per second
+    //decay_factor is the percentage of weight to decay per step
+    Stepped{
+        step_interval_secs: u64,
+        decay_factor: f64,
+    },
+    //weight halves every half_life_secs
+    HalfLife{
+        half_life_secs: u64,
+    },
+    //(elapsed secs, weight factor) points sorted by time, linearly interpolated;
+    //held flat before the first point and after the last
+    Piecewise(Vec<(u64,f64)>),
+    //no decay for the first grace_secs, then the inner model starts from zero
+    Grace{
+        grace_secs: u64,
+        model: Box<DecayModel>,
+    },
+    //replaces the default 10% floor of the inner model
+    Floored{
+        floor: f64,
+        model: Box<DecayModel>,
+    },
+    //inverse of the inner model: weight starts at the floor and climbs back to
+    //full as the inner curve decays, so late votes count for more
+    LateSurge(Box<DecayModel>),
+}
+
+const DEFAULT_FLOOR:Decimal=dec!(0.10);
+const LN_2:Decimal=dec!(0.6931471805599453094172321215);
+
+//why a DecayModel was rejected; parameter names match the variant fields
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum DecayFault{
+    NonFinite{parameter:&'static str},
+    OutOfRange{parameter:&'static str,value:f64},
+    UnsortedPoints{index:usize},               //points[index] is before points[index-1]
+    PointOutOfRange{index:usize,value:f64},    //point values are weight factors, 0..=1
+}
+
+fn fault(fault:DecayFault)->Error{
+    Error::InvalidDecay(fault)
+}
+
+//finite and not negative; a negative rate would grow weights instead of decaying them
+fn rate(parameter:&'static str,value:f64)->Result<()>{
+    if !value.is_finite(){
+        return Err(fault(DecayFault::NonFinite{parameter}));
+    }
+    if value<0.0{
+        return Err(fault(DecayFault::OutOfRange{parameter,value}));
+    }
+    Ok(())
+}
+
+impl DecayModel{
+    //rejects parameters factor would otherwise saturate or silently read as zero
+    pub fn validate(&self)->Result<()>{
+        match self{
+            DecayModel::Linear(value)|DecayModel::Exponential(value)=>rate("rate",*value),
+            DecayModel::Stepped{decay_factor,..}=>rate("decay_factor",*decay_factor),
+            DecayModel::HalfLife{half_life_secs:0}=>{
+                Err(fault(DecayFault::OutOfRange{parameter:"half_life_secs",value:0.0}))
+            }
+            DecayModel::HalfLife{..}=>Ok(()),
+            DecayModel::Piecewise(points)=>{
+                for(index,(time,value)) in points.iter().enumerate(){
+                    if !value.is_finite(){
+                        return Err(fault(DecayFault::NonFinite{parameter:"points"}));
+                    }
+                    if !(0.0..=1.0).contains(value){
+                        return Err(fault(DecayFault::PointOutOfRange{index,value:*value}));
+                    }
+                    if index>0&&*time<points[index-1].0{
+                        return Err(fault(DecayFault::UnsortedPoints{index}));
+                    }
+                }
+                Ok(())
+            }
+            DecayModel::Grace{model,..}|DecayModel::LateSurge(model)=>model.validate(),
+            DecayModel::Floored{floor,model}=>{
+                rate("floor",*floor)?;
+                if *floor>1.0{
+                    return Err(fault(DecayFault::OutOfRange{parameter:"floor",value:*floor}));
+                }
+                model.validate()
+            }
+        }
+    }
+
+    //fraction of the original weight nothing decays below
+    pub fn floor(&self)->Decimal{
+        match self{
+            DecayModel::Floored{floor,..}=>param(*floor),
+            DecayModel::Grace{model,..}|DecayModel::LateSurge(model)=>model.floor(),
+            _=>DEFAULT_FLOOR,
+        }
+    }
+
+    //weight multiplier after elapsed seconds, before the floor is applied;
+    //saturates rather than overflowing when elapsed is huge or negative
+    pub fn factor(&self,elapsed:Decimal)->Decimal{
+        match self{
+            DecayModel::Linear(rate)=>Decimal::ONE.saturating_sub(param(*rate).saturating_mul(elapsed)),
+            DecayModel::Exponential(rate)=>exp(-param(*rate).saturating_mul(elapsed)).unwrap_or(Decimal::MAX),
+            DecayModel::Stepped{step_interval_secs,decay_factor}=>{
+                let steps=if *step_interval_secs==0{
+                    Decimal::ZERO
+                }else{
+                    (elapsed/Decimal::from(*step_interval_secs)).floor()
+                };
+                Decimal::ONE.saturating_sub(param(*decay_factor).saturating_mul(steps))
+            }
+            DecayModel::HalfLife{half_life_secs}=>{
+                if *half_life_secs==0{
+                    return Decimal::ZERO;
+                }
+                exp(-LN_2.saturating_mul(elapsed)/Decimal::from(*half_life_secs)).unwrap_or(Decimal::MAX)
+            }
+            DecayModel::Piecewise(points)=>piecewise(points,elapsed),
+            DecayModel::Grace{grace_secs,model}=>{
+                model.factor((elapsed-Decimal::from(*grace_secs)).max(Decimal::ZERO))
+            }
+            DecayModel::Floored{model,..}=>model.factor(elapsed),
+            DecayModel::LateSurge(model)=>{
+                let decayed=Decimal::ONE-model.factor(elapsed).clamp(Decimal::ZERO,Decimal::ONE);
+                (self.floor()+decayed).min(Decimal::ONE)
+            }
+        }
+    }
+}
+
+fn piecewise(points:&[(u64,f64)],elapsed:Decimal)->Decimal{
+    let (Some(first),Some(last))=(points.first(),points.last()) else{
+        return Decimal::ONE;
+    };
+    if elapsed<=Decimal::from(first.0){
+        return param(first.1);
+    }
+    for pair in points.windows(2){
+        let (t0,t1)=(Decimal::from(pair[0].0),Decimal::from(pair[1].0));
+        if elapsed<=t1{
+            let (f0,f1)=(param(pair[0].1),param(pair[1].1));
+            if t1==t0{
+                return f1;
+            }
+            return f0+(f1-f0)*(elapsed-t0)/(t1-t0);
+        }
+    }
+    param(last.1)
+}
+
+//float convenience wrapper over calculate_weight_decimal
+pub fn calculate_weight(vote_weight:f64,vote_start:DateTime<Utc>,vote_time:DateTime<Utc>,decay_model:DecayModel)->f64{
+    let weight=Decimal::from_f64(vote_weight).unwrap_or(Decimal::ZERO);
+    calculate_weight_decimal(weight,vote_start,vote_time,&decay_model)
+        .to_f64()
+        .unwrap_or(0.0)
+}
+
+//e^x in pure Decimal arithmetic: x=n+f with integer n and 0<=f<1, e^n by
+//squaring, e^f by its Taylor series until terms vanish at 28 digits.
+//Checked against reference values to DECAY_PRECISION (18) decimal places; the
+//result is not rounded, so callers round once at the end. Returns None when the
+//result would not fit in a Decimal and zero when it would round to nothing.
+pub fn exp(x:Decimal)->Option<Decimal>{
+    let n=x.floor();
+    let frac=x-n;
+    let n=match i64::try_from(n){
+        Ok(n)=>n,
+        Err(_) if x.is_sign_negative()=>return Some(Decimal::ZERO),
+        Err(_)=>return None,
+    };
+    if n>=EXP_LIMIT{
+        return None;
+    }
+    if n<=-EXP_LIMIT{
+        return Some(Decimal::ZERO);
+    }
+
+    let mut series=Decimal::ONE;
+    let mut term=Decimal::ONE;
+    for k in 1..=40u32{
+        term=term*frac/Decimal::from(k);
+        if term.is_zero(){
+            break;
+        }
+        series+=term;
+    }
+
+    let mut base=if n<0{E_INV}else{E};
+    let mut remaining=n.unsigned_abs();
+    let mut whole=Decimal::ONE;
+    while remaining>0{
+        if remaining&1==1{
+            whole=whole.checked_mul(base)?;
+        }
+        remaining>>=1;
+        if remaining>0{
+            base=base.checked_mul(base)?;
+        }
+    }
+    whole.checked_mul(series)
+}
+
+//model parameters are f64; from_f64 turns the same bits into the same decimal on every node
+fn param(value:f64)->Decimal{
+    Decimal::from_f64(value).unwrap_or(Decimal::ZERO)
+}
+
+//weight after decay from vote_start to vote_time, never below the model's floor;
+//a vote cast before vote_start can only grow, up to the Decimal range
+pub fn calculate_weight_decimal(vote_weight:Decimal,vote_start:DateTime<Utc>,vote_time:DateTime<Utc>,decay_model:&DecayModel)->Decimal{
+    let elapsed=Decimal::from((vote_time-vote_start).num_seconds());
+    let factor=decay_model.factor(elapsed).max(decay_model.floor());
+    vote_weight.saturating_mul(factor).round_dp(DECAY_PRECISION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_exp_matches_known_values() {
+        let rounded = |x| exp(x).map(|v| v.round_dp(DECAY_PRECISION));
+        assert_eq!(exp(Decimal::ZERO), Some(Decimal::ONE));
+        assert_eq!(exp(Decimal::ONE), Some(E));
+        assert_eq!(rounded(dec!(-1)), Some(E_INV.round_dp(DECAY_PRECISION)));
+        assert_eq!(rounded(dec!(0.5)), Some(dec!(1.648721270700128147)));
+        assert_eq!(rounded(dec!(-2.5)), Some(dec!(0.082084998623898795)));
+        assert_eq!(rounded(dec!(-10)), Some(dec!(0.000045399929762485)));
+        assert_eq!(rounded(dec!(20)), Some(dec!(485165195.409790277969106831)));
+        assert_eq!(exp(dec!(-100)), Some(Decimal::ZERO));
+        assert_eq!(exp(dec!(100)), None);
+    }
+
+    #[test]
+    fn test_decimal_weights_are_exact() {
+        let start = Utc::now();
+        let at = |secs| start + Duration::seconds(secs);
+        let w = dec!(80);
+
+        assert_eq!(calculate_weight_decimal(w, start, at(300), &DecayModel::Linear(0.001)), dec!(56));
+        assert_eq!(calculate_weight_decimal(w, start, at(5000), &DecayModel::Linear(0.001)), dec!(8));
+        let stepped = DecayModel::Stepped { step_interval_secs: 30, decay_factor: 0.1 };
+        assert_eq!(calculate_weight_decimal(w, start, at(90), &stepped), dec!(56));
+        assert_eq!(
+            calculate_weight_decimal(w, start, at(1000), &DecayModel::Exponential(0.001)),
+            dec!(29.430355293715385728)
+        );
+    }
+
+    #[test]
+    fn test_half_life_and_floor() {
+        let start = Utc::now();
+        let model = DecayModel::HalfLife { half_life_secs: 600 };
+        assert_eq!(calculate_weight_decimal(dec!(8), start, start + Duration::seconds(1200), &model), dec!(2));
+        // 8 half-lives would be 1/256, held at the default 10% floor
+        assert_eq!(calculate_weight_decimal(dec!(8), start, start + Duration::seconds(4800), &model), dec!(0.8));
+
+        let floored = DecayModel::Floored { floor: 0.5, model: Box::new(model) };
+        assert_eq!(calculate_weight_decimal(dec!(8), start, start + Duration::seconds(1200), &floored), dec!(4));
+        let no_floor = DecayModel::Floored { floor: 0.0, model: Box::new(DecayModel::Linear(0.01)) };
+        assert_eq!(calculate_weight_decimal(dec!(8), start, start + Duration::seconds(500), &no_floor), dec!(0));
+    }
+
+    #[test]
+    fn test_piecewise_interpolates_between_points() {
+        let start = Utc::now();
+        let model = DecayModel::Piecewise(vec![(60, 1.0), (120, 0.5), (300, 0.2)]);
+        let at = |secs| calculate_weight_decimal(dec!(10), start, start + Duration::seconds(secs), &model);
+        assert_eq!(at(0), dec!(10));
+        assert_eq!(at(60), dec!(10));
+        assert_eq!(at(90), dec!(7.5));
+        assert_eq!(at(210), dec!(3.5));
+        assert_eq!(at(10_000), dec!(2));
+    }
+
+    #[test]
+    fn test_grace_period_delays_decay() {
+        let start = Utc::now();
+        let model = DecayModel::Grace { grace_secs: 120, model: Box::new(DecayModel::Linear(0.001)) };
+        let at = |secs| calculate_weight_decimal(dec!(1), start, start + Duration::seconds(secs), &model);
+        assert_eq!(at(0), dec!(1));
+        assert_eq!(at(120), dec!(1));
+        assert_eq!(at(320), dec!(0.8));
+    }
+
+    #[test]
+    fn test_late_surge_rewards_late_votes() {
+        let start = Utc::now();
+        let model = DecayModel::LateSurge(Box::new(DecayModel::Linear(0.001)));
+        let at = |secs| calculate_weight_decimal(dec!(1), start, start + Duration::seconds(secs), &model);
+        assert_eq!(at(0), dec!(0.1));
+        assert_eq!(at(300), dec!(0.4));
+        assert_eq!(at(900), dec!(1));
+        assert_eq!(at(5000), dec!(1));
+    }
+
+    #[test]
+    fn test_extreme_inputs_saturate_instead_of_panicking() {
+        let start = Utc::now();
+        // a vote from before the start grows past e^64; it saturates instead of overflowing
+        let early = calculate_weight(2.0, start, start - Duration::days(1), DecayModel::Exponential(0.001));
+        assert!(early.is_finite() && early > 2.0);
+        let growing = calculate_weight(2.0, start, start + Duration::days(1), DecayModel::Exponential(-1.0));
+        assert!(growing.is_finite() && growing > 2.0);
+
+        // an exponent too large for i64 means fully decayed, not maximal
+        let later = start + Duration::days(365);
+        assert_eq!(calculate_weight_decimal(dec!(2), start, later, &DecayModel::Exponential(1e15)), dec!(0.2));
+        let unfloored = DecayModel::Floored { floor: 0.0, model: Box::new(DecayModel::Exponential(1e15)) };
+        assert_eq!(calculate_weight_decimal(dec!(2), start, later, &unfloored), Decimal::ZERO);
+        assert_eq!(calculate_weight_decimal(dec!(2), start, later, &DecayModel::Linear(1e28)), dec!(0.2));
+        assert_eq!(exp(dec!(-64)), Some(Decimal::ZERO));
+        assert_eq!(exp(Decimal::MIN), Some(Decimal::ZERO));
+        assert_eq!(exp(Decimal::MAX), None);
+    }
+
+    #[test]
+    fn test_validate_rejects_negative_and_non_finite_rates() {
+        assert!(DecayModel::Exponential(0.001).validate().is_ok());
+        assert_eq!(
+            DecayModel::Exponential(-1.0).validate(),
+            Err(Error::InvalidDecay(DecayFault::OutOfRange { parameter: "rate", value: -1.0 }))
+        );
+        assert_eq!(
+            DecayModel::Linear(f64::NAN).validate(),
+            Err(Error::InvalidDecay(DecayFault::NonFinite { parameter: "rate" }))
+        );
+        let nested = DecayModel::Grace {
+            grace_secs: 60,
+            model: Box::new(DecayModel::Stepped { step_interval_secs: 30, decay_factor: f64::INFINITY }),
+        };
+        assert_eq!(nested.validate(), Err(Error::InvalidDecay(DecayFault::NonFinite { parameter: "decay_factor" })));
+        let floored = DecayModel::Floored { floor: 1.5, model: Box::new(DecayModel::Linear(0.01)) };
+        assert_eq!(
+            floored.validate(),
+            Err(Error::InvalidDecay(DecayFault::OutOfRange { parameter: "floor", value: 1.5 }))
+        );
+    }
+
+    #[test]
+    fn test_half_life_saturates_and_piecewise_is_validated() {
+        let start = Utc::now();
+        let model = DecayModel::HalfLife { half_life_secs: 1 };
+        assert!(model.validate().is_ok());
+        assert_eq!(
+            DecayModel::HalfLife { half_life_secs: 0 }.validate(),
+            Err(Error::InvalidDecay(DecayFault::OutOfRange { parameter: "half_life_secs", value: 0.0 }))
+        );
+        let early = calculate_weight(2.0, start, start - Duration::days(1), model.clone());
+        assert!(early.is_finite() && early > 2.0);
+        assert_eq!(calculate_weight_decimal(dec!(2), start, start + Duration::days(365), &model), dec!(0.2));
+
+        assert!(DecayModel::Piecewise(vec![(0, 1.0), (60, 0.5), (60, 0.25)]).validate().is_ok());
+        assert_eq!(
+            DecayModel::Piecewise(vec![(0, 1.0), (120, 0.5), (60, 0.25)]).validate(),
+            Err(Error::InvalidDecay(DecayFault::UnsortedPoints { index: 2 }))
+        );
+        assert_eq!(
+            DecayModel::Piecewise(vec![(0, 1.0), (60, 2.0)]).validate(),
+            Err(Error::InvalidDecay(DecayFault::PointOutOfRange { index: 1, value: 2.0 }))
+        );
+        let nested = DecayModel::LateSurge(Box::new(DecayModel::Piecewise(vec![(0, f64::NAN)])));
+        assert_eq!(nested.validate(), Err(Error::InvalidDecay(DecayFault::NonFinite { parameter: "points" })));
+    }
+
+    #[test]
+    fn test_original_models_match_float_baseline() {
+        // the f64 formulas calculate_weight used before weights were computed in Decimal
+        fn baseline(weight: f64, elapsed: f64, model: &DecayModel) -> f64 {
+            let decayed = match model {
+                DecayModel::Linear(rate) => weight * (1.0 - rate * elapsed),
+                DecayModel::Exponential(rate) => weight * (-rate * elapsed).exp(),
+                DecayModel::Stepped { step_interval_secs, decay_factor } => {
+                    weight * (1.0 - decay_factor * (elapsed / *step_interval_secs as f64).floor())
+                }
+                _ => unreachable!(),
+            };
+            decayed.max(weight * 0.10)
+        }
+
+        let start = Utc::now();
+        let models = [
+            DecayModel::Linear(0.0007),
+            DecayModel::Exponential(0.0013),
+            DecayModel::Stepped { step_interval_secs: 45, decay_factor: 0.07 },
+        ];
+        for model in models {
+            for secs in [0, 1, 59, 600, 1799, 7200] {
+                let expected = baseline(3.0, secs as f64, &model);
+                let actual = calculate_weight(3.0, start, start + Duration::seconds(secs), model.clone());
+                assert!((actual - expected).abs() < 1e-9, "{:?} at {}s: {} != {}", model, secs, actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_models_serialize() {
+        let model = DecayModel::Floored {
+            floor: 0.25,
+            model: Box::new(DecayModel::Grace {
+                grace_secs: 60,
+                model: Box::new(DecayModel::LateSurge(Box::new(DecayModel::Piecewise(vec![(0, 1.0), (600, 0.0)])))),
+            }),
+        };
+        let json = serde_json::to_string(&model).unwrap();
+        assert_eq!(serde_json::from_str::<DecayModel>(&json).unwrap(), model);
+        assert_eq!(
+            serde_json::from_str::<DecayModel>(r#"{"half_life":{"half_life_secs":600}}"#).unwrap(),
+            DecayModel::HalfLife { half_life_secs: 600 }
+        );
+        assert_eq!(serde_json::to_string(&DecayModel::Linear(0.01)).unwrap(), r#"{"linear":0.01}"#);
+    }
+
+    #[test]
+    fn test_zero_decay_linear() {
+        let vote_weight = 50.0;
+        let start = Utc::now();
+        let later = start + Duration::minutes(5);
+        let decay_model = DecayModel::Linear(0.0); // no decay
+
+        let result = calculate_weight(vote_weight, start, later, decay_model);
+        assert_eq!(result, vote_weight);
+    }
+
+    #[test]
+    fn test_immediate_vote_exponential() {
+        let vote_weight = 20.0;
+        let start = Utc::now();
+        let later = start; // no time has passed
+        let decay_model = DecayModel::Exponential(0.01);
+
+        let result = calculate_weight(vote_weight, start, later, decay_model);
+        assert_eq!(result, vote_weight); // no decay yet
+    }
+
+    #[test]
+    fn test_high_decay_linear_reaches_floor() {
+        let vote_weight = 100.0;
+        let start = Utc::now();
+        let later = start + Duration::minutes(200); // long enough to decay below floor
+        let decay_model = DecayModel::Linear(0.01); // 1% per minute
+
+        let result = calculate_weight(vote_weight, start, later, decay_model);
+        assert_eq!(result, 10.0); // floor is 10%
+    }
+
+    #[test]
+    fn test_large_step_decay() {
+        let vote_weight = 80.0;
+        let start = Utc::now();
+        let later = start + Duration::seconds(90); // 90 seconds elapsed
+        let decay_model = DecayModel::Stepped {
+            step_interval_secs: 30,
+            decay_factor: 0.1, // 10% per step
+        };
+
+        // 90s / 30s = 3 steps → 30% decay → 80 * 0.7 = 56
+        let result = calculate_weight(vote_weight, start, later, decay_model);
+        assert!((result - 56.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_exponential_decay_heavy() {
+        let vote_weight = 40.0;
+        let start = Utc::now();
+        let later = start + Duration::seconds(100);
+        let decay_model = DecayModel::Exponential(0.1); // fast decay
+
+        let result = calculate_weight(vote_weight, start, later, decay_model);
+        // Should be significantly decayed, but still >= floor (40 * 0.1 = 4.0)
+        assert!((4.0..=40.0).contains(&result));
+    }
+}
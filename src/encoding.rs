@@ -0,0 +1,243 @@
+//! Canonical binary encoding for everything that gets signed or hashed.
+//!
+//! Rules, so other implementations can reproduce the bytes exactly:
+//!
+//! * every structure starts with an ASCII domain tag that names it and its
+//!   version, e.g. `chronovote/vote/v1`, written like a string
+//! * integers are fixed width, big endian
+//! * strings are a `u32` byte length followed by UTF-8; anything longer than
+//!   `u32::MAX` bytes cannot be encoded
+//! * timestamps are `i64` unix seconds followed by `u32` nanoseconds
+//! * decimals are normalized (no trailing zeros), then written as the `i128`
+//!   mantissa followed by the `u32` scale
+//! * no floats anywhere
+//!
+//! `Vote` v1, tag `chronovote/vote/v1`:
+//! proposal_id str | voter_id str | validator_id str | vote_time ts |
+//! vote_weight decimal | choice | nonce u64.
+//! `choice` is a `u8` tag: 0 yes, 1 no, 2 abstain, 3 option followed by the
+//! `u32` option index.
+//!
+//! `BlockHeader` v1, tag `chronovote/block-header/v1`:
+//! index u64 | timestamp ts | prev_hash str | payload sha256 (32 bytes) |
+//! `u8` 0, or `u8` 1 followed by the 32 byte votes merkle root.
+
+use chrono::{DateTime, TimeZone, Utc};
+use rust_decimal::Decimal;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+    /// First value that could not be encoded; reported by `finish`
+    error: Option<Error>,
+}
+
+impl Encoder {
+    pub fn new(domain: &[u8]) -> Self {
+        let mut enc = Self::default();
+        enc.bytes(domain);
+        enc
+    }
+
+    pub fn u8(&mut self, v: u8) -> &mut Self {
+        self.buf.push(v);
+        self
+    }
+
+    pub fn u32(&mut self, v: u32) -> &mut Self {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+        self
+    }
+
+    pub fn u64(&mut self, v: u64) -> &mut Self {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+        self
+    }
+
+    pub fn i64(&mut self, v: i64) -> &mut Self {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+        self
+    }
+
+    pub fn raw(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn str(&mut self, s: &str) -> &mut Self {
+        self.bytes(s.as_bytes())
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        match u32::try_from(bytes.len()) {
+            Ok(len) => self.u32(len).raw(bytes),
+            Err(_) => {
+                let error = Error::InvalidEncoding(format!("{} bytes do not fit a u32 length", bytes.len()));
+                self.error.get_or_insert(error);
+                self
+            }
+        }
+    }
+
+    pub fn timestamp(&mut self, t: &DateTime<Utc>) -> &mut Self {
+        self.i64(t.timestamp()).u32(t.timestamp_subsec_nanos())
+    }
+
+    pub fn decimal(&mut self, d: &Decimal) -> &mut Self {
+        let d = d.normalize();
+        self.raw(&d.mantissa().to_be_bytes()).u32(d.scale())
+    }
+
+    /// The encoded bytes, or the error for the first value that didn't fit.
+    pub fn finish(&mut self) -> Result<Vec<u8>> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(std::mem::take(&mut self.buf)),
+        }
+    }
+}
+
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Checks and skips the domain tag, which must match exactly.
+    pub fn new(buf: &'a [u8], domain: &[u8]) -> Result<Self> {
+        let mut dec = Self { buf, pos: 0 };
+        let tag = dec.u32().and_then(|len| dec.take(len as usize));
+        if tag.ok() != Some(domain) {
+            return Err(Error::InvalidEncoding(format!(
+                "expected domain {}",
+                String::from_utf8_lossy(domain)
+            )));
+        }
+        Ok(dec)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|end| *end <= self.buf.len());
+        let end = end.ok_or_else(|| Error::InvalidEncoding("unexpected end of input".into()))?;
+        let out = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn raw<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    pub fn str(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| Error::InvalidEncoding("invalid utf-8".into()))
+    }
+
+    pub fn timestamp(&mut self) -> Result<DateTime<Utc>> {
+        let secs = self.i64()?;
+        let nanos = self.u32()?;
+        Utc.timestamp_opt(secs, nanos)
+            .single()
+            .ok_or_else(|| Error::InvalidEncoding("timestamp out of range".into()))
+    }
+
+    pub fn decimal(&mut self) -> Result<Decimal> {
+        let mantissa = i128::from_be_bytes(self.raw::<16>()?);
+        let scale = self.u32()?;
+        let d = Decimal::try_from_i128_with_scale(mantissa, scale)
+            .map_err(|_| Error::InvalidEncoding("decimal out of range".into()))?;
+        if d.normalize().scale() != scale {
+            return Err(Error::InvalidEncoding("decimal not normalized".into()));
+        }
+        Ok(d)
+    }
+
+    /// Fails if anything is left over; canonical input has no trailing bytes.
+    pub fn finish(self) -> Result<()> {
+        if self.pos != self.buf.len() {
+            return Err(Error::InvalidEncoding("trailing bytes".into()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_primitives_round_trip() {
+        let t = Utc.timestamp_opt(1_700_000_000, 123).unwrap();
+        let bytes = Encoder::new(b"test/v1")
+            .u8(7)
+            .u32(8)
+            .u64(9)
+            .i64(-10)
+            .str("héllo")
+            .timestamp(&t)
+            .decimal(&dec!(1.50))
+            .finish()
+            .unwrap();
+
+        let mut d = Decoder::new(&bytes, b"test/v1").unwrap();
+        assert_eq!(d.u8().unwrap(), 7);
+        assert_eq!(d.u32().unwrap(), 8);
+        assert_eq!(d.u64().unwrap(), 9);
+        assert_eq!(d.i64().unwrap(), -10);
+        assert_eq!(d.str().unwrap(), "héllo");
+        assert_eq!(d.timestamp().unwrap(), t);
+        assert_eq!(d.decimal().unwrap(), dec!(1.5));
+        d.finish().unwrap();
+    }
+
+    #[test]
+    fn test_equal_decimals_encode_identically() {
+        let a = Encoder::new(b"").decimal(&dec!(1.0)).finish().unwrap();
+        let b = Encoder::new(b"").decimal(&dec!(1.000)).finish().unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_decoder_rejects_bad_input() {
+        let tagged = |domain: &[u8], rest: &[u8]| {
+            let mut bytes = Encoder::new(domain).finish().unwrap();
+            bytes.extend_from_slice(rest);
+            bytes
+        };
+        assert!(Decoder::new(&tagged(b"other/v1", b""), b"test/v1").is_err());
+        // a longer tag that merely starts with the expected one
+        assert!(Decoder::new(&tagged(b"test/v10", b""), b"test/v1").is_err());
+        assert!(Decoder::new(b"test/v1", b"test/v1").is_err());
+
+        let bytes = tagged(b"test/v1", b"\x00\x00");
+        let mut d = Decoder::new(&bytes, b"test/v1").unwrap();
+        assert!(d.u32().is_err());
+
+        let bytes = tagged(b"test/v1", b"\x00");
+        let d = Decoder::new(&bytes, b"test/v1").unwrap();
+        assert!(d.finish().is_err());
+
+        // 10 with scale 1 is 1.0, which should have been written as 1 with scale 0
+        let bytes = Encoder::new(b"").raw(&10i128.to_be_bytes()).u32(1).finish().unwrap();
+        assert!(Decoder::new(&bytes, b"").unwrap().decimal().is_err());
+    }
+}
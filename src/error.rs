@@ -0,0 +1,130 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+use crate::blockchain::ChainFault;
+use crate::decay::DecayFault;
+use crate::registry::Role;
+use crate::threshold::ThresholdFault;
+use crate::window::ProposalState;
+
+/// Errors returned by the chronovote public API.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A vote signature did not verify against its public key.
+    InvalidSignature { voter_id: String },
+    /// No proposal is registered under the given id.
+    UnknownProposal(String),
+    /// The ledger failed its integrity check at the given block.
+    InvalidChain { index: usize, fault: ChainFault },
+    /// A proposal cannot move between these two lifecycle states.
+    InvalidTransition { from: ProposalState, to: ProposalState },
+    /// A vote arrived while the proposal was not accepting votes.
+    VoteRejected { proposal_id: String, state: ProposalState },
+    /// The tally has not reached a decision yet.
+    Undecided(String),
+    /// An I/O or encoding error from the ledger store.
+    Io(String),
+    /// A stored record failed its checksum or could not be decoded.
+    CorruptLedger { segment: u64, offset: u64 },
+    /// A block payload was written with a version this build cannot read.
+    UnsupportedPayloadVersion(u16),
+    /// A versioned block payload did not match its schema.
+    InvalidPayload(String),
+    /// The id has no active key in the registry.
+    UnknownIdentity { role: Role, id: String },
+    /// The id already has an active key.
+    AlreadyRegistered { role: Role, id: String },
+    /// The key carried by a vote is not the one registered for its id.
+    KeyMismatch { role: Role, id: String },
+    /// A registry event carried a missing or malformed public key.
+    InvalidKey { role: Role, id: String },
+    /// Batch verification failed; holds the indices of the bad votes.
+    InvalidSignatures(Vec<usize>),
+    /// A validator countersignature did not verify or named the wrong validator.
+    InvalidAttestation { voter_id: String, validator_id: String },
+    /// The voter's claimed time is too far from the validator's attested time.
+    TimestampDrift { voter_id: String, claimed: DateTime<Utc>, attested: DateTime<Utc> },
+    /// Too few validators agreed on when a vote was received.
+    NoTimestampQuorum { voter_id: String, attestations: usize, required: usize },
+    /// A threshold model or policy was configured with unusable parameters.
+    InvalidThreshold(ThresholdFault),
+    /// A decay model was configured with unusable parameters.
+    InvalidDecay(DecayFault),
+    /// Bytes could not be decoded into the expected structure.
+    InvalidEncoding(String),
+    /// The exact same signed vote was submitted again.
+    DuplicateVote { proposal_id: String, voter_id: String },
+    /// A key signed two different votes on one proposal.
+    Equivocation { proposal_id: String, voter_id: String },
+    /// A voter's nonce was already used on another proposal.
+    NonceReused { voter_id: String, nonce: u64, proposal_id: String },
+    /// A voter's nonce is below one they already used; nonces must increase.
+    StaleNonce { voter_id: String, nonce: u64, last: u64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidSignature { voter_id } => write!(f, "invalid signature on vote from {}", voter_id),
+            Error::UnknownProposal(id) => write!(f, "unknown proposal {}", id),
+            Error::InvalidChain { index, fault } => {
+                write!(f, "blockchain integrity check failed at block {}: {:?}", index, fault)
+            }
+            Error::InvalidTransition { from, to } => write!(f, "invalid proposal transition {:?} -> {:?}", from, to),
+            Error::VoteRejected { proposal_id, state } => {
+                write!(f, "vote rejected: proposal {} is {:?}", proposal_id, state)
+            }
+            Error::Undecided(id) => write!(f, "proposal {} has not been decided yet", id),
+            Error::Io(msg) => write!(f, "ledger i/o error: {}", msg),
+            Error::CorruptLedger { segment, offset } => {
+                write!(f, "corrupt ledger record in segment {} at offset {}", segment, offset)
+            }
+            Error::UnsupportedPayloadVersion(v) => write!(f, "unsupported block payload version {}", v),
+            Error::InvalidPayload(msg) => write!(f, "invalid block payload: {}", msg),
+            Error::UnknownIdentity { role, id } => write!(f, "{:?} {} has no registered key", role, id),
+            Error::AlreadyRegistered { role, id } => write!(f, "{:?} {} is already registered", role, id),
+            Error::KeyMismatch { role, id } => write!(f, "key does not match the one registered for {:?} {}", role, id),
+            Error::InvalidKey { role, id } => write!(f, "invalid public key for {:?} {}", role, id),
+            Error::InvalidSignatures(indices) => write!(f, "invalid signatures at positions {:?}", indices),
+            Error::InvalidAttestation { voter_id, validator_id } => {
+                write!(f, "invalid attestation by {} on vote from {}", validator_id, voter_id)
+            }
+            Error::TimestampDrift { voter_id, claimed, attested } => write!(
+                f,
+                "vote from {} claims {} but was attested at {}",
+                voter_id, claimed, attested
+            ),
+            Error::NoTimestampQuorum { voter_id, attestations, required } => write!(
+                f,
+                "vote from {} has {} agreeing timestamp attestations, {} required",
+                voter_id, attestations, required
+            ),
+            Error::InvalidThreshold(fault) => write!(f, "invalid threshold configuration: {:?}", fault),
+            Error::InvalidDecay(fault) => write!(f, "invalid decay model: {:?}", fault),
+            Error::InvalidEncoding(msg) => write!(f, "invalid encoding: {}", msg),
+            Error::DuplicateVote { proposal_id, voter_id } => {
+                write!(f, "duplicate vote from {} on proposal {}", voter_id, proposal_id)
+            }
+            Error::Equivocation { proposal_id, voter_id } => {
+                write!(f, "{} signed conflicting votes on proposal {}", voter_id, proposal_id)
+            }
+            Error::NonceReused { voter_id, nonce, proposal_id } => {
+                write!(f, "{} already used nonce {} on proposal {}", voter_id, nonce, proposal_id)
+            }
+            Error::StaleNonce { voter_id, nonce, last } => {
+                write!(f, "{} sent nonce {} after already using {}", voter_id, nonce, last)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,75 @@
+//! ChronoVote: time-decay weighted voting with dynamic threshold escalation.
+//!
+//! The crate is split into small modules that can be used on their own or
+//! together through [`prelude`]:
+//!
+//! * [`clock`] – injectable time source
+//! * [`encoding`] – canonical binary encoding of signed and hashed data
+//! * [`decay`] – decay models and the raw weight function
+//! * [`weight`] – weighted votes, caching, history and reputation bonuses
+//! * [`voter`] – votes and their ed25519 signatures
+//! * [`attestation`] – validator countersigned receipt times and quorum medians
+//! * [`registry`] – voter and validator key bindings
+//! * [`pool`] – accepted votes with replay and equivocation detection
+//! * [`tally`] – decay-weighted tally that decides a proposal
+//! * [`threshold`] – time based threshold escalation
+//! * [`threshold_prog`] – progression profiles and proposal-type requirements
+//! * [`policy`] – one threshold policy composed from all of the above
+//! * [`verify`] – safety and liveness checks for escalation rules
+//! * [`window`] – voting windows and the proposal manager
+//! * [`blockchain`] – the append-only ledger votes are recorded on
+//! * [`payload`] – typed, versioned block contents
+//! * [`merkle`] – merkle trees and vote inclusion proofs
+//! * [`store`] – crash-safe on-disk ledger segments
+
+pub mod attestation;
+pub mod blockchain;
+pub mod clock;
+pub mod decay;
+pub mod encoding;
+pub mod error;
+pub mod merkle;
+pub mod payload;
+pub mod policy;
+pub mod pool;
+pub mod registry;
+pub mod store;
+pub mod tally;
+pub mod threshold;
+pub mod threshold_prog;
+pub mod verify;
+pub mod voter;
+pub mod weight;
+pub mod window;
+
+pub use error::{Error, Result};
+
+/// Commonly used types, re-exported for `use chronovote::prelude::*`.
+pub mod prelude {
+    pub use crate::attestation::{
+        Attestation, AttestedVote, DriftMonitor, QuorumAttestedVote, QuorumPolicy, QuorumTime,
+    };
+    pub use crate::blockchain::{Block, BlockHeader, Blockchain, ChainFault};
+    pub use crate::clock::{Clock, FixedClock, SimulatedClock, SystemClock};
+    pub use crate::decay::{calculate_weight, calculate_weight_decimal, DecayFault, DecayModel};
+    pub use crate::error::{Error, Result};
+    pub use crate::merkle::{prove_vote, MerkleProof, MerkleTree};
+    pub use crate::payload::BlockPayload;
+    pub use crate::policy::{ThresholdDecision, ThresholdPolicy, ThresholdSource};
+    pub use crate::pool::{Equivocation, EquivocationEvidence, VotePool};
+    pub use crate::registry::{KeyAction, KeyEvent, Registry, Role};
+    pub use crate::store::LedgerStore;
+    pub use crate::tally::{Tally, TallyOutcome, TallyStatus};
+    pub use crate::threshold::{
+        threshold_at, threshold_at_resolution, threshold_within, Resolution, ThresholdBounds, ThresholdEmergency,
+        ThresholdFault, ThresholdModel, MAX_THRESHOLD, MIN_THRESHOLD,
+    };
+    pub use crate::threshold_prog::{
+        recommend_profile, requirement_for_type, scheduled_base_threshold, ProgressionProfile,
+        ProposalHistory, Proposaltype, ThresholdRequirement,
+    };
+    pub use crate::verify::{Property, Verifier, Violation};
+    pub use crate::voter::{count_choices, verify_batch, Choice, ChoiceCounts, SignedVote, Vote};
+    pub use crate::weight::{DecayBasis, WeightEngine, WeightedVote};
+    pub use crate::window::{ExtensionPolicy, ProposalManager, ProposalState, VotingSession, VotingWindow};
+}
@@ -1,63 +1,106 @@
-mod threshold;
-mod threshold_prog;
-mod voter;
-mod weight;
-mod window;
-mod decay;
-mod blockchain;
-
-use std::thread::sleep;
-
-use crate::decay::*;
-use crate::threshold::*;
-use crate::threshold_prog::*;
-use crate::voter::*;
-use crate::weight::*;
-use crate::window::*;
-use crate::blockchain::*;
-
-use chrono::Utc;
+use chronovote::prelude::*;
+
 use ed25519_dalek::SigningKey;
-use rand::rngs::OsRng;
 use rust_decimal_macros::dec;
-use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 
 fn main() {
     println!("🪙 Blockchain-based Time-Decay Threshold Voting Simulation\n");
 
     // === Initialize Blockchain ===
-    let mut blockchain = Blockchain::new();
+    // Set CHRONOVOTE_LEDGER to a directory to keep the ledger across runs
+    let mut store = std::env::var("CHRONOVOTE_LEDGER")
+        .ok()
+        .map(|dir| LedgerStore::open(dir).expect("failed to open ledger"));
+    let mut blockchain = match store.as_mut() {
+        Some(store) => store
+            .load_chain(std::sync::Arc::new(SystemClock))
+            .expect("failed to load ledger"),
+        None => Blockchain::new(),
+    };
+    let first_new_block = blockchain.blocks.len();
 
     // === Proposal setup ===
-    let now = Utc::now();
     let proposal_id = "proposal_1".to_string();
     let proposer_id = "admin".to_string();
     let voting_window = VotingWindow::Medium;
 
     let mut proposal_manager = ProposalManager::new(60);
-    proposal_manager.add_proposal(proposal_id.clone(), proposer_id.clone(), voting_window);
+    proposal_manager.extension_policy = Some(ExtensionPolicy::default());
 
+    let decay_model = DecayModel::Exponential(0.001);
+    let mut threshold_policy = ThresholdPolicy::new(ThresholdModel::Linear(0.01));
+    threshold_policy.profile = Some(ProgressionProfile::Conservative);
+    threshold_policy.time_of_day = true;
+    let mut tally = proposal_manager
+        .create_proposal(proposal_id.clone(), proposer_id.clone(), voting_window, threshold_policy, &decay_model)
+        .expect("invalid proposal configuration");
+
+    let now = proposal_manager.session(&proposal_id).unwrap().vote_start;
     let vote_start = now;
 
     // === Simulate voters ===
-    let mut csprng = OsRng;
-    let voters = vec!["Alice", "Bob", "Charlie", "Dave", "Eve"];
-    let validators = vec!["Val1", "Val2", "Val3", "Val4", "Val5"];
+    let voters = ["Alice", "Bob", "Charlie", "Dave", "Eve"];
+    let validators = ["Val1", "Val2", "Val3", "Val4", "Val5"];
+    let choices = [Choice::Yes, Choice::Yes, Choice::No, Choice::Yes, Choice::Abstain];
 
-    let decay_model = DecayModel::Exponential(0.001);
     let mut weight_engine = WeightEngine::new();
 
+    // === Register keys ===
+    // Demo keys are derived from the ids so a persisted ledger keeps working
+    // across runs; only ids not yet on the ledger get registered
+    let mut registry = Registry::from_chain(&blockchain).expect("failed to replay registry");
+    let voter_keys: Vec<SigningKey> = voters.iter().map(|id| demo_key(id)).collect();
+    let validator_keys: Vec<SigningKey> = validators.iter().map(|id| demo_key(id)).collect();
+    let identities = voters
+        .iter()
+        .zip(&voter_keys)
+        .map(|(id, key)| (Role::Voter, *id, key.verifying_key()))
+        .chain(
+            validators
+                .iter()
+                .zip(&validator_keys)
+                .map(|(id, key)| (Role::Validator, *id, key.verifying_key())),
+        );
+    for (role, id, key) in identities {
+        if registry.key_for(role, id).is_some() {
+            continue;
+        }
+        let event = registry.register(role, id, key, now).unwrap();
+        blockchain.add_payload(&BlockPayload::KeyChange(event));
+    }
+    println!("🔑 Registered {} voters and {} validators\n", voters.len(), validators.len());
+
     // Reputation bonuses
     weight_engine.set_reputation(&"Alice".to_string(), dec!(0.1));
     weight_engine.set_reputation(&"Bob".to_string(), dec!(0.05));
     weight_engine.set_reputation(&"Eve".to_string(), dec!(0.2));
 
+    let session_end = proposal_manager.session(&proposal_id).unwrap().end_time();
+
+    // Check the escalation rules against the decay model before opening the vote
+    let verifier = Verifier::new(
+        tally.policy.clone(),
+        decay_model.clone(),
+        vote_start,
+        session_end - vote_start,
+    );
+    for violation in verifier.verify() {
+        println!(
+            "⚠️ Escalation rule violates {:?} at {} instants, first at {}",
+            violation.property, violation.occurrences, violation.counterexamples[0]
+        );
+    }
+    tally.eligible_voters = Some(voters.len());
+
+    let mut pool = VotePool::new();
     let mut signed_votes = vec![];
+    let mut attested_votes = vec![];
 
     println!("📥 Collecting votes...\n");
 
     for (i, voter_name) in voters.iter().enumerate() {
-        let signing_key = SigningKey::generate(&mut csprng);
+        let signing_key = &voter_keys[i];
 
         let vote_time = now;
 
@@ -65,26 +108,42 @@ fn main() {
             voter_id: voter_name.to_string(),
             validator_id: validators[i].to_string(),
             vote_time,
-            vote_weight: 1.0,
+            vote_weight: dec!(1.0),
+            choice: choices[i],
+            proposal_id: proposal_id.clone(),
+            nonce: 1,
         };
 
-        let signed_vote = vote.sign(&signing_key);
-
-        if signed_vote.verify() {
-            println!(
-                "✅ {}'s vote verified at {}",
-                voter_name, vote.vote_time
-            );
-            signed_votes.push(signed_vote);
-        } else {
-            println!("❌ {}'s vote INVALID.", voter_name);
+        let signed_vote = vote.sign(signing_key).expect("failed to sign vote");
+        // The named validator countersigns the time it received the vote
+        let attested_vote =
+            AttestedVote::attest(signed_vote.clone(), &validator_keys[i], now).expect("failed to attest vote");
+
+        match proposal_manager
+            .check_vote(&proposal_id, attested_vote.attested_time())
+            .and_then(|_| registry.verify_attested(&attested_vote))
+            .and_then(|_| pool.submit(&registry, signed_vote.clone()))
+        {
+            Ok(()) => {
+                println!(
+                    "✅ {}'s vote ({:?}) attested by {} at {}",
+                    voter_name,
+                    vote.choice,
+                    attested_vote.attestation.validator_id,
+                    attested_vote.attested_time()
+                );
+                signed_votes.push(signed_vote);
+                attested_votes.push(attested_vote);
+            }
+            Err(err) => println!("❌ {}", err),
         }
     }
 
     // === Compute weights ===
     println!("\n📊 Computing effective weights...\n");
 
-    for signed_vote in &signed_votes {
+    for attested_vote in &attested_votes {
+        let signed_vote = &attested_vote.vote;
         let rep_bonus = weight_engine
             .reputation
             .get(&signed_vote.vote.voter_id)
@@ -93,57 +152,109 @@ fn main() {
 
         let weighted_vote = WeightedVote {
             voter_id: signed_vote.vote.voter_id.clone(),
-            vote_time: signed_vote.vote.vote_time,
-            orig_weight: dec!(1.0),
+            vote_time: attested_vote.attested_time(),
+            orig_weight: signed_vote.vote.vote_weight,
             decay_model: decay_model.clone(),
             reputation_bonus: rep_bonus,
         };
 
-        let eff_weight =
-            weight_engine.calculate_and_cache(&weighted_vote, &vote_start, now);
-
-        println!(
-            "📊 {} effective weight: {:.3} (at {})",
-            weighted_vote.voter_id, eff_weight, weighted_vote.vote_time
-        );
+        match weight_engine.calculate_attested(&registry, attested_vote, &decay_model, &vote_start, now) {
+            Ok(eff_weight) => {
+                println!(
+                    "📊 {} effective weight: {:.3} (at {})",
+                    weighted_vote.voter_id, eff_weight, weighted_vote.vote_time
+                );
+                tally.add_vote(signed_vote.vote.choice, weighted_vote);
+            }
+            Err(err) => println!("❌ {}", err),
+        }
     }
 
     // === Threshold check ===
-    println!("\n🔍 Checking threshold requirement...\n");
+    println!("\n🔍 Tallying decay-weighted votes...\n");
 
-    let total_votes = signed_votes.len();
-    let yes_votes = signed_votes.len(); // assume all YES
-    let req = requirement_for_type(Proposaltype::Normal);
+    let counts = count_choices(&signed_votes);
+    println!(
+        "🗳️ Yes {} | No {} | Abstain {}",
+        counts.yes, counts.no, counts.abstain
+    );
+
+    proposal_manager
+        .tick(&proposal_id, &mut tally, now)
+        .unwrap();
+
+    // Fast-forward to the close of the window, where the tally is decided
+    let closed_at = proposal_manager.session(&proposal_id).unwrap().end_time();
+    let outcome = tally.evaluate(closed_at);
 
     println!(
-        "📝 Threshold: min_percentage {:.2}, min_yes_votes {}",
-        req.min_percentage, req.max_abs
+        "⚖️ Weighted yes {:.3} | no {:.3} | share {:.3} vs threshold {:.2} from {:?} (margin {:.3})",
+        outcome.yes_weight,
+        outcome.no_weight,
+        outcome.yes_share,
+        outcome.threshold,
+        outcome.threshold_source,
+        outcome.margin
     );
 
-    let result = if req.is_met(yes_votes, total_votes) {
-        println!("🎉 Proposal PASSED.");
-        "PASSED"
-    } else {
-        println!("🚫 Proposal FAILED.");
-        "FAILED"
+    let decision = match proposal_manager.finalize(&proposal_id, &outcome, closed_at) {
+        Ok(state) => {
+            if state == ProposalState::Passed {
+                println!("🎉 Proposal PASSED.");
+            } else {
+                println!("🚫 Proposal {:?}.", state);
+            }
+            Some(state)
+        }
+        Err(err) => {
+            println!("⏳ {}", err);
+            None
+        }
     };
 
     // === Save proposal to blockchain ===
     println!("\n⛓️ Adding proposal to blockchain...\n");
 
-    let vote_jsons: Vec<String> = signed_votes
-        .iter()
-        .map(|v| serde_json::to_string(&v.vote).unwrap())
-        .collect();
+    let session = proposal_manager.session(&proposal_id).unwrap();
+
+    blockchain.add_payload(&BlockPayload::ProposalCreated {
+        proposal_id: proposal_id.clone(),
+        proposer_id: proposer_id.clone(),
+        window_secs: session.voting_window.duration().num_seconds(),
+        created_at: session.vote_start,
+        decay_model: Some(decay_model.clone()),
+    });
 
-    let data = json!({
-        "proposal_id": proposal_id,
-        "votes": vote_jsons,
-        "result": result
-    })
-    .to_string();
+    let votes_block = blockchain.blocks.len();
+    let votes_payload = BlockPayload::AttestedVotesBatch {
+        proposal_id: proposal_id.clone(),
+        votes: attested_votes.clone(),
+    };
+    blockchain
+        .add_vote_block(votes_payload.encode(), &signed_votes)
+        .expect("failed to commit votes");
 
-    blockchain.add_blocks(data);
+    for equivocation in pool.drain_evidence() {
+        let evidence = equivocation.to_evidence().expect("failed to encode evidence");
+        blockchain.add_payload(&BlockPayload::Equivocation(evidence));
+    }
+
+    if let Some(state) = decision {
+        blockchain.add_payload(&BlockPayload::ProposalFinalized {
+            proposal_id: proposal_id.clone(),
+            state,
+            yes_weight: outcome.yes_weight,
+            no_weight: outcome.no_weight,
+            abstain_weight: outcome.abstain_weight,
+            threshold: outcome.threshold,
+        });
+    }
+
+    if let Some(store) = store.as_mut() {
+        for block in &blockchain.blocks[first_new_block..] {
+            store.append(block).expect("failed to persist block");
+        }
+    }
 
     // === Print blockchain ===
     println!("📜 Blockchain Ledger:\n");
@@ -152,22 +263,37 @@ fn main() {
             "🧱 Block {} | Time: {} | Hash: {} | Prev: {}",
             blk.index, blk.timestamp, blk.hash, blk.prev_hash
         );
+        if let Some(root) = &blk.votes_root {
+            println!("🌳 Votes root: {}", root);
+        }
 
-        if blk.index > 0 {
-            if let Ok(json_val) = serde_json::from_str::<Value>(&blk.data) {
-                println!("{}", serde_json::to_string_pretty(&json_val).unwrap());
-            }
+        match blk.payload() {
+            Ok(BlockPayload::Legacy(data)) => println!("{}", data),
+            Ok(payload) => println!("{}", serde_json::to_string_pretty(&payload).unwrap()),
+            Err(err) => println!("⚠️ {}", err),
         }
         println!("---");
     }
 
-    if blockchain.is_valid() {
-        println!("✅ Blockchain integrity: VALID");
-    } else {
-        println!("🚨 Blockchain integrity: INVALID");
+    if let Some(proof) = prove_vote(&signed_votes, "Alice") {
+        let block = &blockchain.blocks[votes_block];
+        println!(
+            "🔎 Alice's vote included in block {}: {}",
+            block.index,
+            block.contains_vote(&signed_votes[0], &proof)
+        );
+    }
+
+    match blockchain.validate() {
+        Ok(()) => println!("✅ Blockchain integrity: VALID"),
+        Err(err) => println!("🚨 Blockchain integrity: INVALID ({})", err),
     }
 }
 
+/// Deterministic demo key for an id. Never use this outside the simulation.
+fn demo_key(id: &str) -> SigningKey {
+    SigningKey::from_bytes(&Sha256::digest(format!("chronovote-demo/{id}")).into())
+}
 
 #[cfg(test)]
 mod simulation_tests {
@@ -183,12 +309,15 @@ mod simulation_tests {
             voter_id: "TestVoter".to_string(),
             validator_id: "TestValidator".to_string(),
             vote_time: Utc::now(),
-            vote_weight: 1.0,
+            vote_weight: dec!(1.0),
+            choice: Choice::Yes,
+            proposal_id: "p1".into(),
+            nonce: 0,
         };
 
         let mut csprng = OsRng;
         let signing_key = SigningKey::generate(&mut csprng);
-        let signed_vote = vote.sign(&signing_key);
+        let signed_vote = vote.sign(&signing_key).unwrap();
 
         assert!(signed_vote.verify());
     }
@@ -0,0 +1,173 @@
+use sha2::{Digest, Sha256};
+
+use crate::error::Result;
+use crate::voter::SignedVote;
+
+pub type Hash = [u8; 32];
+
+// Leaves and inner nodes are hashed under different prefixes so a leaf can
+// never be passed off as an inner node.
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Path from a leaf to the root. Each step names the sibling hash and which
+/// side of the current node it sits on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub siblings: Vec<(Side, Hash)>,
+}
+
+impl MerkleProof {
+    pub fn root_for(&self, leaf_data: &[u8]) -> Hash {
+        self.siblings.iter().fold(leaf_hash(leaf_data), |acc, (side, sibling)| match side {
+            Side::Left => node_hash(sibling, &acc),
+            Side::Right => node_hash(&acc, sibling),
+        })
+    }
+
+    pub fn verify(&self, root: &Hash, leaf_data: &[u8]) -> bool {
+        &self.root_for(leaf_data) == root
+    }
+}
+
+/// Binary Merkle tree. An unpaired node at the end of a level is carried up
+/// unchanged rather than duplicated.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    levels: Vec<Vec<Hash>>, // levels[0] are the leaves, the last level is the root
+}
+
+impl MerkleTree {
+    pub fn new<T: AsRef<[u8]>>(leaves: &[T]) -> Self {
+        let mut levels = vec![leaves.iter().map(|leaf| leaf_hash(leaf.as_ref())).collect::<Vec<_>>()];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    pub fn from_votes(votes: &[SignedVote]) -> Result<Self> {
+        let leaves = votes.iter().map(|vote| vote.encode()).collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(&leaves))
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Root of the tree; an empty tree has the all-zero root.
+    pub fn root(&self) -> Hash {
+        self.levels.last().and_then(|level| level.first()).copied().unwrap_or([0u8; 32])
+    }
+
+    pub fn proof(&self, leaf_index: usize) -> Option<MerkleProof> {
+        if leaf_index >= self.len() {
+            return None;
+        }
+        let mut siblings = Vec::new();
+        let mut idx = leaf_index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = idx ^ 1;
+            if sibling < level.len() {
+                let side = if sibling < idx { Side::Left } else { Side::Right };
+                siblings.push((side, level[sibling]));
+            }
+            idx /= 2;
+        }
+        Some(MerkleProof { leaf_index, siblings })
+    }
+}
+
+/// Inclusion proof for the vote cast by `voter_id`, built over the same vote
+/// list (and order) that was committed to the block.
+pub fn prove_vote(votes: &[SignedVote], voter_id: &str) -> Option<MerkleProof> {
+    let index = votes.iter().position(|vote| vote.vote.voter_id == voter_id)?;
+    MerkleTree::from_votes(votes).ok()?.proof(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: usize) -> Vec<Vec<u8>> {
+        (0..n).map(|i| format!("leaf-{}", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_every_leaf_proves_against_root() {
+        for n in 1..=9 {
+            let data = leaves(n);
+            let tree = MerkleTree::new(&data);
+            for (i, leaf) in data.iter().enumerate() {
+                let proof = tree.proof(i).unwrap();
+                assert!(proof.verify(&tree.root(), leaf), "leaf {} of {}", i, n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_proof_rejects_wrong_leaf() {
+        let data = leaves(5);
+        let tree = MerkleTree::new(&data);
+        let proof = tree.proof(2).unwrap();
+        assert!(!proof.verify(&tree.root(), b"leaf-3"));
+        assert!(!proof.verify(&[0u8; 32], &data[2]));
+    }
+
+    #[test]
+    fn test_single_leaf_and_empty_tree() {
+        let tree = MerkleTree::new(&[b"only".to_vec()]);
+        assert_eq!(tree.root(), leaf_hash(b"only"));
+        assert!(tree.proof(0).unwrap().siblings.is_empty());
+        assert!(tree.proof(1).is_none());
+
+        let empty = MerkleTree::new::<Vec<u8>>(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.root(), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_leaf_is_not_an_inner_node() {
+        let data = leaves(2);
+        let tree = MerkleTree::new(&data);
+        let mut concatenated = leaf_hash(&data[0]).to_vec();
+        concatenated.extend_from_slice(&leaf_hash(&data[1]));
+        assert_ne!(leaf_hash(&concatenated), tree.root());
+    }
+}
@@ -0,0 +1,247 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::attestation::AttestedVote;
+use crate::decay::DecayModel;
+use crate::error::{Error, Result};
+use crate::pool::EquivocationEvidence;
+use crate::registry::KeyEvent;
+use crate::voter::{SignedVote, Vote};
+use crate::window::ProposalState;
+
+/// Version written into every encoded payload.
+///
+/// Bump it on every schema change, additive ones included (a new variant or a
+/// new field), so an older reader reports `UnsupportedPayloadVersion` instead
+/// of failing to parse. `decode` keeps reading every earlier version.
+///
+/// - 1: proposals, unsigned vote batches, finalization, validator set and
+///   parameter changes.
+/// - 2: adds signed and attested vote batches, key changes, equivocation
+///   evidence and `ProposalCreated.decay_model`.
+pub const PAYLOAD_VERSION: u16 = 2;
+
+/// Typed contents of a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockPayload {
+    ProposalCreated {
+        proposal_id: String,
+        proposer_id: String,
+        window_secs: i64,
+        created_at: DateTime<Utc>,
+        /// How vote weights decay for this proposal; absent in older blocks.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        decay_model: Option<DecayModel>,
+    },
+    /// Unsigned votes, as written before signed votes were serializable.
+    VotesBatch {
+        proposal_id: String,
+        votes: Vec<Vote>,
+    },
+    SignedVotesBatch {
+        proposal_id: String,
+        votes: Vec<SignedVote>,
+    },
+    /// Signed votes with their validators' receipt times.
+    AttestedVotesBatch {
+        proposal_id: String,
+        votes: Vec<AttestedVote>,
+    },
+    ProposalFinalized {
+        proposal_id: String,
+        state: ProposalState,
+        yes_weight: Decimal,
+        no_weight: Decimal,
+        abstain_weight: Decimal,
+        threshold: f64,
+    },
+    ValidatorSetChange {
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    ParameterChange {
+        name: String,
+        value: serde_json::Value,
+    },
+    KeyChange(KeyEvent),
+    Equivocation(EquivocationEvidence),
+    /// Block data written before payloads were typed (including the genesis
+    /// block); kept verbatim.
+    #[serde(skip)]
+    Legacy(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    v: u16,
+    payload: serde_json::Value,
+}
+
+impl BlockPayload {
+    /// `{"v":<version>,"payload":{"type":...}}`; legacy data is written back unchanged.
+    pub fn encode(&self) -> String {
+        match self {
+            BlockPayload::Legacy(data) => data.clone(),
+            payload => serde_json::to_string(&Envelope {
+                v: PAYLOAD_VERSION,
+                payload: serde_json::to_value(payload).expect("payload serializes"),
+            })
+            .expect("envelope serializes"),
+        }
+    }
+
+    pub fn decode(data: &str) -> Result<Self> {
+        let envelope = match serde_json::from_str::<Envelope>(data) {
+            Ok(envelope) => envelope,
+            Err(_) => return Ok(BlockPayload::Legacy(data.to_string())),
+        };
+        match envelope.v {
+            1..=PAYLOAD_VERSION => serde_json::from_value(envelope.payload).map_err(|e| Error::InvalidPayload(e.to_string())),
+            v => Err(Error::UnsupportedPayloadVersion(v)),
+        }
+    }
+
+    pub fn proposal_id(&self) -> Option<&str> {
+        match self {
+            BlockPayload::ProposalCreated { proposal_id, .. }
+            | BlockPayload::VotesBatch { proposal_id, .. }
+            | BlockPayload::SignedVotesBatch { proposal_id, .. }
+            | BlockPayload::AttestedVotesBatch { proposal_id, .. }
+            | BlockPayload::ProposalFinalized { proposal_id, .. } => Some(proposal_id),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_round_trip_every_variant() {
+        let payloads = vec![
+            BlockPayload::ProposalCreated {
+                proposal_id: "p1".into(),
+                proposer_id: "admin".into(),
+                window_secs: 1800,
+                created_at: Utc::now(),
+                decay_model: Some(DecayModel::Grace {
+                    grace_secs: 60,
+                    model: Box::new(DecayModel::HalfLife { half_life_secs: 600 }),
+                }),
+            },
+            BlockPayload::VotesBatch {
+                proposal_id: "p1".into(),
+                votes: vec![],
+            },
+            BlockPayload::SignedVotesBatch {
+                proposal_id: "p1".into(),
+                votes: vec![crate::voter::Vote {
+                    voter_id: "Alice".into(),
+                    validator_id: "Val1".into(),
+                    vote_time: Utc::now(),
+                    vote_weight: dec!(1),
+                    choice: crate::voter::Choice::Yes,
+                    proposal_id: "p1".into(),
+                    nonce: 1,
+                }
+                .sign(&ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]))
+                .unwrap()],
+            },
+            BlockPayload::AttestedVotesBatch {
+                proposal_id: "p1".into(),
+                votes: vec![
+                    AttestedVote::attest(
+                        crate::voter::Vote {
+                            voter_id: "Alice".into(),
+                            validator_id: "Val1".into(),
+                            vote_time: Utc::now(),
+                            vote_weight: dec!(1),
+                            choice: crate::voter::Choice::No,
+                            proposal_id: "p1".into(),
+                            nonce: 2,
+                        }
+                        .sign(&ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]))
+                        .unwrap(),
+                        &ed25519_dalek::SigningKey::from_bytes(&[9u8; 32]),
+                        Utc::now(),
+                    )
+                    .unwrap(),
+                ],
+            },
+            BlockPayload::ProposalFinalized {
+                proposal_id: "p1".into(),
+                state: ProposalState::Passed,
+                yes_weight: dec!(3.15),
+                no_weight: dec!(1.0),
+                abstain_weight: dec!(1.2),
+                threshold: 0.51,
+            },
+            BlockPayload::ValidatorSetChange {
+                added: vec!["Val6".into()],
+                removed: vec!["Val1".into()],
+            },
+            BlockPayload::ParameterChange {
+                name: "grace_period_secs".into(),
+                value: serde_json::json!(60),
+            },
+            BlockPayload::Equivocation(EquivocationEvidence {
+                proposal_id: "p1".into(),
+                voter_id: "Alice".into(),
+                first: "AAAA".into(),
+                second: "BBBB".into(),
+            }),
+            BlockPayload::KeyChange(KeyEvent {
+                role: crate::registry::Role::Voter,
+                id: "Alice".into(),
+                action: crate::registry::KeyAction::Revoke,
+                public_key: None,
+                at: Utc::now(),
+            }),
+        ];
+
+        for payload in payloads {
+            let encoded = payload.encode();
+            assert!(encoded.starts_with("{\"v\":2,"));
+            assert_eq!(BlockPayload::decode(&encoded).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn test_legacy_data_is_readable() {
+        assert_eq!(
+            BlockPayload::decode("Genesis Block").unwrap(),
+            BlockPayload::Legacy("Genesis Block".into())
+        );
+
+        let old = r#"{"proposal_id":"proposal_1","votes":[],"result":"PASSED"}"#;
+        let decoded = BlockPayload::decode(old).unwrap();
+        assert_eq!(decoded, BlockPayload::Legacy(old.into()));
+        assert_eq!(decoded.encode(), old);
+    }
+
+    #[test]
+    fn test_unknown_version_is_rejected() {
+        let future = r#"{"v":9,"payload":{"type":"something_new"}}"#;
+        assert_eq!(BlockPayload::decode(future), Err(Error::UnsupportedPayloadVersion(9)));
+        let next = r#"{"v":3,"payload":{"type":"votes_batch","proposal_id":"p1","votes":[]}}"#;
+        assert_eq!(BlockPayload::decode(next), Err(Error::UnsupportedPayloadVersion(3)));
+        let zero = r#"{"v":0,"payload":{"type":"votes_batch","proposal_id":"p1","votes":[]}}"#;
+        assert_eq!(BlockPayload::decode(zero), Err(Error::UnsupportedPayloadVersion(0)));
+
+        let broken = r#"{"v":1,"payload":{"type":"votes_batch"}}"#;
+        assert!(matches!(BlockPayload::decode(broken), Err(Error::InvalidPayload(_))));
+    }
+
+    #[test]
+    fn test_proposal_without_decay_model_still_decodes() {
+        let data = r#"{"v":1,"payload":{"type":"proposal_created","proposal_id":"p1","proposer_id":"admin","window_secs":1800,"created_at":"2024-01-01T00:00:00Z"}}"#;
+        match BlockPayload::decode(data).unwrap() {
+            BlockPayload::ProposalCreated { decay_model, .. } => assert_eq!(decay_model, None),
+            other => panic!("unexpected payload {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use chrono::{DateTime, Timelike, Utc};
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::threshold::{
+    base_threshold, Resolution, ThresholdBounds, ThresholdEmergency, ThresholdFault, ThresholdModel,
+};
+use crate::threshold_prog::{self, requirement_for_type, scheduled_base_threshold, ProgressionProfile, Proposaltype};
+
+/// A part of a [`ThresholdPolicy`] that can set the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThresholdSource {
+    Base,
+    Profile,
+    Schedule,
+    ProposalType,
+    Emergency,
+    /// Every component was below the floor.
+    Floor,
+    /// The strictest component was above the ceiling.
+    Ceiling,
+}
+
+/// One evaluated threshold and how it was reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdDecision {
+    pub threshold: f64,
+    pub source: ThresholdSource,
+    /// Every component that was evaluated, before clamping.
+    pub components: Vec<(ThresholdSource, f64)>,
+    /// Yes votes required by head count, from the proposal type.
+    pub min_yes_votes: usize,
+}
+
+impl fmt::Display for ThresholdDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3} set by {:?} (", self.threshold, self.source)?;
+        for (i, (source, value)) in self.components.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?} {:.3}", source, value)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Everything that decides a proposal's threshold, in one place.
+///
+/// The base curve always applies; the optional components each produce their
+/// own threshold and the strictest one wins, clamped to the proposal's
+/// [`ThresholdBounds`] once at the end. An emergency override replaces all of
+/// them and is clamped the same way. Elapsed time is measured from the start
+/// of voting.
+#[derive(Debug, Clone)]
+pub struct ThresholdPolicy {
+    pub base: ThresholdModel,
+    pub profile: Option<ProgressionProfile>,
+    /// Apply [`scheduled_base_threshold`] for the UTC hour of evaluation.
+    pub time_of_day: bool,
+    pub proposal_type: Option<Proposaltype>,
+    pub emergency: Option<ThresholdEmergency>,
+    /// Reject base curves whose threshold can go down over time.
+    pub require_monotonic: bool,
+    /// How finely the base curve sees elapsed time.
+    pub resolution: Resolution,
+    /// Floor and ceiling for this proposal, applied to every component.
+    pub bounds: ThresholdBounds,
+}
+
+impl ThresholdPolicy {
+    pub fn new(base: ThresholdModel) -> Self {
+        Self {
+            base,
+            profile: None,
+            time_of_day: false,
+            proposal_type: None,
+            emergency: None,
+            require_monotonic: true,
+            resolution: Resolution::default(),
+            bounds: ThresholdBounds::default(),
+        }
+    }
+
+    /// Checks the base curve and the emergency value; see [`ThresholdModel::validate`].
+    pub fn validate(&self) -> Result<()> {
+        self.bounds.validate()?;
+        if self.require_monotonic {
+            self.base.validate_monotonic(&self.bounds)?;
+        } else {
+            self.base.validate()?;
+        }
+        if let Some(ThresholdEmergency::Emergency(value)) = &self.emergency {
+            if !value.is_finite() {
+                return Err(Error::InvalidThreshold(ThresholdFault::NonFinite { parameter: "emergency" }));
+            }
+            if !(0.0..=1.0).contains(value) {
+                return Err(Error::InvalidThreshold(ThresholdFault::OutOfRange {
+                    parameter: "emergency",
+                    value: *value,
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// `participation` is the fraction of eligible voters that have voted;
+    /// only the adaptive profile looks at it.
+    pub fn evaluate(&self, start: DateTime<Utc>, now: DateTime<Utc>, participation: f64) -> ThresholdDecision {
+        let min_yes_votes = self
+            .proposal_type
+            .clone()
+            .map(|p| requirement_for_type(p).max_abs)
+            .unwrap_or(0);
+
+        let components = match &self.emergency {
+            Some(ThresholdEmergency::Emergency(requested)) => vec![(ThresholdSource::Emergency, *requested)],
+            None => self.components(start, now, participation),
+        };
+
+        // clamped once, here; the first component wins a tie, so the base
+        // curve is credited when nothing is stricter
+        let (source, strictest) = components
+            .iter()
+            .fold(components[0], |best, c| if c.1 > best.1 { *c } else { best });
+        let (threshold, source) = if strictest > self.bounds.ceiling {
+            (self.bounds.ceiling, ThresholdSource::Ceiling)
+        } else if strictest < self.bounds.floor {
+            (self.bounds.floor, ThresholdSource::Floor)
+        } else {
+            (strictest, source)
+        };
+        ThresholdDecision { threshold, source, components, min_yes_votes }
+    }
+
+    /// Every configured component, unclamped and base curve first.
+    fn components(&self, start: DateTime<Utc>, now: DateTime<Utc>, participation: f64) -> Vec<(ThresholdSource, f64)> {
+        let elapsed_secs = (now - start).num_seconds().max(0) as u64;
+        let base = base_threshold(start, now, &self.base, self.resolution, &self.bounds);
+        let mut components = vec![(ThresholdSource::Base, base)];
+        if let Some(profile) = &self.profile {
+            components.push((
+                ThresholdSource::Profile,
+                threshold_prog::profile_threshold(profile, elapsed_secs, participation),
+            ));
+        }
+        if self.time_of_day {
+            components.push((ThresholdSource::Schedule, scheduled_base_threshold(now.hour())));
+        }
+        if let Some(proposal_type) = &self.proposal_type {
+            components.push((
+                ThresholdSource::ProposalType,
+                requirement_for_type(proposal_type.clone()).min_percentage,
+            ));
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threshold::MAX_THRESHOLD;
+    use chrono::{Duration, TimeZone};
+
+    fn at_hour(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_base_curve_alone() {
+        let start = at_hour(12);
+        let decision = ThresholdPolicy::new(ThresholdModel::Linear(0.01)).evaluate(start, start + Duration::minutes(10), 1.0);
+        assert!((decision.threshold - 0.61).abs() < 1e-9);
+        assert_eq!(decision.source, ThresholdSource::Base);
+        assert_eq!(decision.components.len(), 1);
+        assert_eq!(decision.min_yes_votes, 0);
+    }
+
+    #[test]
+    fn test_floor_and_ceiling_are_reported() {
+        let start = at_hour(12);
+        let mut policy = ThresholdPolicy::new(ThresholdModel::Linear(0.01));
+        let held = policy.evaluate(start, start + Duration::minutes(100), 1.0);
+        assert_eq!(held.source, ThresholdSource::Ceiling);
+        assert_eq!(held.threshold, MAX_THRESHOLD);
+        assert!((held.components[0].1 - 1.51).abs() < 1e-9);
+
+        policy.base = ThresholdModel::Linear(-0.01);
+        policy.require_monotonic = false;
+        let sagging = policy.evaluate(start, start + Duration::minutes(10), 1.0);
+        assert_eq!(sagging.source, ThresholdSource::Floor);
+        assert_eq!(sagging.threshold, 0.51);
+        assert!((sagging.components[0].1 - 0.41).abs() < 1e-9);
+
+        // an emergency over the ceiling is credited to the ceiling as well
+        policy.emergency = Some(ThresholdEmergency::Emergency(0.95));
+        let emergency = policy.evaluate(start, start, 1.0);
+        assert_eq!(emergency.source, ThresholdSource::Ceiling);
+        assert_eq!(emergency.components, vec![(ThresholdSource::Emergency, 0.95)]);
+    }
+
+    #[test]
+    fn test_strictest_component_wins() {
+        let mut policy = ThresholdPolicy::new(ThresholdModel::Linear(0.01));
+        policy.profile = Some(ProgressionProfile::Adaptive);
+        policy.time_of_day = true;
+
+        // at 03:00 the night schedule (0.70) beats the base curve
+        let night = policy.evaluate(at_hour(3), at_hour(3) + Duration::minutes(5), 0.5);
+        assert_eq!(night.source, ThresholdSource::Schedule);
+        assert_eq!(night.threshold, 0.70);
+
+        // low participation pushes the adaptive profile to 0.70 as well; the schedule came later
+        let day = policy.evaluate(at_hour(12), at_hour(12) + Duration::minutes(5), 0.1);
+        assert_eq!(day.source, ThresholdSource::Profile);
+        assert_eq!(day.threshold, 0.70);
+        assert_eq!(day.components.len(), 3);
+
+        policy.proposal_type = Some(Proposaltype::Critical);
+        let critical = policy.evaluate(at_hour(12), at_hour(12), 1.0);
+        assert_eq!(critical.source, ThresholdSource::ProposalType);
+        assert_eq!(critical.threshold, 0.80);
+        assert_eq!(critical.min_yes_votes, 15);
+    }
+
+    #[test]
+    fn test_emergency_replaces_components() {
+        let mut policy = ThresholdPolicy::new(ThresholdModel::Linear(0.01));
+        policy.proposal_type = Some(Proposaltype::Emergency);
+        policy.profile = Some(ProgressionProfile::Aggresive);
+        let early = policy.evaluate(at_hour(12), at_hour(12) + Duration::minutes(10), 1.0);
+        assert_eq!(early.threshold, MAX_THRESHOLD);
+        assert_eq!(early.source, ThresholdSource::ProposalType);
+
+        policy.emergency = Some(ThresholdEmergency::Emergency(0.75));
+        let emergency = policy.evaluate(at_hour(12), at_hour(12) + Duration::minutes(120), 1.0);
+        assert_eq!(emergency.threshold, 0.75);
+        assert_eq!(emergency.source, ThresholdSource::Emergency);
+        assert_eq!(emergency.to_string(), "0.750 set by Emergency (Emergency 0.750)");
+    }
+
+    #[test]
+    fn test_validate_policy() {
+        let mut policy = ThresholdPolicy::new(ThresholdModel::StepFn(vec![(0, 0.8), (5, 0.6)]));
+        assert_eq!(
+            policy.validate(),
+            Err(Error::InvalidThreshold(ThresholdFault::DecreasingStep { index: 1 }))
+        );
+        policy.require_monotonic = false;
+        assert!(policy.validate().is_ok());
+
+        policy.emergency = Some(ThresholdEmergency::Emergency(1.5));
+        assert_eq!(
+            policy.validate(),
+            Err(Error::InvalidThreshold(ThresholdFault::OutOfRange { parameter: "emergency", value: 1.5 }))
+        );
+    }
+
+    #[test]
+    fn test_bounds_apply_to_every_component() {
+        let mut policy = ThresholdPolicy::new(ThresholdModel::Linear(0.0));
+        policy.bounds = ThresholdBounds { floor: 0.5, ceiling: 0.75 };
+        policy.proposal_type = Some(Proposaltype::Critical);
+        let critical = policy.evaluate(at_hour(12), at_hour(12), 1.0);
+        assert_eq!(critical.threshold, 0.75);
+        assert_eq!(critical.source, ThresholdSource::Ceiling);
+
+        policy.proposal_type = None;
+        assert_eq!(policy.evaluate(at_hour(12), at_hour(12), 1.0).threshold, 0.5);
+
+        policy.bounds.ceiling = 0.95;
+        policy.emergency = Some(ThresholdEmergency::Emergency(0.95));
+        assert_eq!(policy.evaluate(at_hour(12), at_hour(12), 1.0).threshold, 0.95);
+
+        // the profile is held to the policy's ceiling, not a fixed 90%
+        policy.emergency = None;
+        policy.bounds.ceiling = 0.97;
+        policy.profile = Some(ProgressionProfile::Aggresive);
+        let late = policy.evaluate(at_hour(12), at_hour(12) + Duration::minutes(20), 1.0);
+        assert_eq!(late.source, ThresholdSource::Profile);
+        assert!((late.threshold - 0.91).abs() < 1e-9);
+        let capped = policy.evaluate(at_hour(12), at_hour(12) + Duration::minutes(40), 1.0);
+        assert_eq!(capped.source, ThresholdSource::Ceiling);
+        assert_eq!(capped.threshold, 0.97);
+
+        policy.bounds.floor = 0.99;
+        assert!(matches!(
+            policy.validate(),
+            Err(Error::InvalidThreshold(ThresholdFault::InvalidBounds { .. }))
+        ));
+    }
+}
@@ -0,0 +1,294 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{Error, Result};
+use crate::registry::{Registry, Role};
+use crate::voter::SignedVote;
+
+/// Two different votes on one proposal, validly signed by the same voter and key.
+#[derive(Debug, Clone)]
+pub struct Equivocation {
+    pub first: SignedVote,
+    pub second: SignedVote,
+}
+
+impl Equivocation {
+    /// True when the pair really is proof of equivocation and not a forgery.
+    pub fn is_valid(&self) -> bool {
+        self.first.verify()
+            && self.second.verify()
+            && self.first.public_key == self.second.public_key
+            && self.first.vote.voter_id == self.second.vote.voter_id
+            && self.first.vote.proposal_id == self.second.vote.proposal_id
+            && matches!((self.first.encode(), self.second.encode()), (Ok(a), Ok(b)) if a != b)
+    }
+
+    pub fn to_evidence(&self) -> Result<EquivocationEvidence> {
+        Ok(EquivocationEvidence {
+            proposal_id: self.first.vote.proposal_id.clone(),
+            voter_id: self.first.vote.voter_id.clone(),
+            first: BASE64.encode(self.first.encode()?),
+            second: BASE64.encode(self.second.encode()?),
+        })
+    }
+}
+
+/// Ledger form of an [`Equivocation`]; both votes are kept in their signed encoding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EquivocationEvidence {
+    pub proposal_id: String,
+    pub voter_id: String,
+    pub first: String,
+    pub second: String,
+}
+
+impl EquivocationEvidence {
+    pub fn decode(&self) -> Result<Equivocation> {
+        let decode = |b64: &str| {
+            BASE64
+                .decode(b64)
+                .map_err(|e| Error::InvalidEncoding(e.to_string()))
+                .and_then(|bytes| SignedVote::decode(&bytes))
+        };
+        Ok(Equivocation {
+            first: decode(&self.first)?,
+            second: decode(&self.second)?,
+        })
+    }
+}
+
+fn vote_id(vote: &SignedVote) -> Result<[u8; 32]> {
+    Ok(Sha256::digest(vote.encode()?).into())
+}
+
+/// Accepted votes, with replay and double-vote detection.
+///
+/// * the exact same signed vote twice is a duplicate
+/// * a second, different vote from the same voter and key on the same
+///   proposal is equivocation; both votes are kept as evidence and the first
+///   one stands
+/// * a second vote for the same voter under a different key is rejected; which
+///   key is genuine is for the registry to say
+/// * each voter's nonces must strictly increase across all their votes
+///
+/// Votes are checked against the registry before they claim a slot or a
+/// nonce, so an unregistered key cannot lock a voter out.
+#[derive(Debug, Default)]
+pub struct VotePool {
+    seen: HashSet<[u8; 32]>,
+    by_voter: HashMap<(String, String), SignedVote>,
+    /// Highest nonce per voter and the proposal it was used on
+    nonces: HashMap<String, (u64, String)>,
+    evidence: Vec<Equivocation>,
+}
+
+impl VotePool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn submit(&mut self, registry: &Registry, vote: SignedVote) -> Result<()> {
+        registry.verify_vote(&vote)?;
+        let id = vote_id(&vote)?;
+        if self.seen.contains(&id) {
+            return Err(Error::DuplicateVote {
+                proposal_id: vote.vote.proposal_id.clone(),
+                voter_id: vote.vote.voter_id.clone(),
+            });
+        }
+
+        let voter_id = &vote.vote.voter_id;
+        let slot = (vote.vote.proposal_id.clone(), voter_id.clone());
+        if let Some(first) = self.by_voter.get(&slot) {
+            if first.public_key != vote.public_key {
+                return Err(Error::KeyMismatch { role: Role::Voter, id: voter_id.clone() });
+            }
+            self.evidence.push(Equivocation {
+                first: first.clone(),
+                second: vote.clone(),
+            });
+            self.seen.insert(id);
+            return Err(Error::Equivocation {
+                proposal_id: vote.vote.proposal_id.clone(),
+                voter_id: vote.vote.voter_id.clone(),
+            });
+        }
+
+        let nonce = vote.vote.nonce;
+        if let Some((last, used_on)) = self.nonces.get(voter_id) {
+            if nonce == *last {
+                return Err(Error::NonceReused {
+                    voter_id: voter_id.clone(),
+                    nonce,
+                    proposal_id: used_on.clone(),
+                });
+            }
+            if nonce < *last {
+                return Err(Error::StaleNonce { voter_id: voter_id.clone(), nonce, last: *last });
+            }
+        }
+
+        self.seen.insert(id);
+        self.nonces.insert(voter_id.clone(), (nonce, vote.vote.proposal_id.clone()));
+        self.by_voter.insert(slot, vote);
+        Ok(())
+    }
+
+    /// Accepted votes for one proposal, in no particular order.
+    pub fn votes_for(&self, proposal_id: &str) -> Vec<&SignedVote> {
+        self.by_voter
+            .iter()
+            .filter(|((pid, _), _)| pid == proposal_id)
+            .map(|(_, vote)| vote)
+            .collect()
+    }
+
+    pub fn evidence(&self) -> &[Equivocation] {
+        &self.evidence
+    }
+
+    /// Hands over the collected evidence, e.g. to write it to the ledger.
+    pub fn drain_evidence(&mut self) -> Vec<Equivocation> {
+        std::mem::take(&mut self.evidence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voter::{Choice, Vote};
+    use chrono::Utc;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+    use rust_decimal_macros::dec;
+
+    fn vote(proposal_id: &str, nonce: u64, choice: Choice) -> Vote {
+        Vote {
+            voter_id: "Alice".into(),
+            validator_id: "Val1".into(),
+            vote_time: Utc::now(),
+            vote_weight: dec!(1.0),
+            choice,
+            proposal_id: proposal_id.into(),
+            nonce,
+        }
+    }
+
+    fn registry(key: &SigningKey) -> Registry {
+        let mut registry = Registry::new();
+        let registered_at = Utc::now() - chrono::Duration::hours(1);
+        registry.register(Role::Voter, "Alice", key.verifying_key(), registered_at).unwrap();
+        registry
+            .register(Role::Validator, "Val1", SigningKey::generate(&mut OsRng).verifying_key(), registered_at)
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_duplicate_submission_rejected() {
+        let key = SigningKey::generate(&mut OsRng);
+        let registry = registry(&key);
+        let signed = vote("p1", 1, Choice::Yes).sign(&key).unwrap();
+        let mut pool = VotePool::new();
+
+        pool.submit(&registry, signed.clone()).unwrap();
+        assert_eq!(
+            pool.submit(&registry, signed),
+            Err(Error::DuplicateVote { proposal_id: "p1".into(), voter_id: "Alice".into() })
+        );
+        assert!(pool.evidence().is_empty());
+        assert_eq!(pool.votes_for("p1").len(), 1);
+    }
+
+    #[test]
+    fn test_equivocation_keeps_evidence() {
+        let key = SigningKey::generate(&mut OsRng);
+        let registry = registry(&key);
+        let mut pool = VotePool::new();
+
+        pool.submit(&registry, vote("p1", 1, Choice::Yes).sign(&key).unwrap()).unwrap();
+        assert_eq!(
+            pool.submit(&registry, vote("p1", 2, Choice::No).sign(&key).unwrap()),
+            Err(Error::Equivocation { proposal_id: "p1".into(), voter_id: "Alice".into() })
+        );
+
+        assert_eq!(pool.votes_for("p1")[0].vote.choice, Choice::Yes);
+        let evidence = pool.drain_evidence();
+        assert_eq!(evidence.len(), 1);
+        assert!(evidence[0].is_valid());
+
+        let restored = evidence[0].to_evidence().unwrap().decode().unwrap();
+        assert!(restored.is_valid());
+        assert_eq!(restored.second.vote.choice, Choice::No);
+    }
+
+    #[test]
+    fn test_nonce_reuse_across_proposals() {
+        let key = SigningKey::generate(&mut OsRng);
+        let registry = registry(&key);
+        let mut pool = VotePool::new();
+
+        pool.submit(&registry, vote("p1", 7, Choice::Yes).sign(&key).unwrap()).unwrap();
+        assert_eq!(
+            pool.submit(&registry, vote("p2", 7, Choice::Yes).sign(&key).unwrap()),
+            Err(Error::NonceReused { voter_id: "Alice".into(), nonce: 7, proposal_id: "p1".into() })
+        );
+        pool.submit(&registry, vote("p2", 8, Choice::Yes).sign(&key).unwrap()).unwrap();
+        assert_eq!(
+            pool.submit(&registry, vote("p3", 3, Choice::Yes).sign(&key).unwrap()),
+            Err(Error::StaleNonce { voter_id: "Alice".into(), nonce: 3, last: 8 })
+        );
+    }
+
+    #[test]
+    fn test_slot_is_per_voter_not_per_key() {
+        let key = SigningKey::generate(&mut OsRng);
+        let other = SigningKey::generate(&mut OsRng);
+        let mut registry = registry(&key);
+        let mut pool = VotePool::new();
+
+        pool.submit(&registry, vote("p1", 1, Choice::Yes).sign(&key).unwrap()).unwrap();
+        // rotating to a fresh key does not buy the voter a second vote
+        registry.rotate(Role::Voter, "Alice", other.verifying_key(), Utc::now()).unwrap();
+        assert_eq!(
+            pool.submit(&registry, vote("p1", 2, Choice::No).sign(&other).unwrap()),
+            Err(Error::KeyMismatch { role: Role::Voter, id: "Alice".into() })
+        );
+        assert!(pool.evidence().is_empty());
+        assert_eq!(pool.votes_for("p1").len(), 1);
+    }
+
+    #[test]
+    fn test_forged_vote_is_not_accepted() {
+        let key = SigningKey::generate(&mut OsRng);
+        let registry = registry(&key);
+        let mut signed = vote("p1", 1, Choice::Yes).sign(&key).unwrap();
+        signed.vote.proposal_id = "p2".into();
+
+        let mut pool = VotePool::new();
+        assert!(pool.submit(&registry, signed).is_err());
+        assert!(pool.votes_for("p2").is_empty());
+    }
+
+    #[test]
+    fn test_unregistered_key_cannot_claim_slot_or_nonce() {
+        let alice = SigningKey::generate(&mut OsRng);
+        let mallory = SigningKey::generate(&mut OsRng);
+        let registry = registry(&alice);
+        let mut pool = VotePool::new();
+
+        assert_eq!(
+            pool.submit(&registry, vote("p1", u64::MAX, Choice::No).sign(&mallory).unwrap()),
+            Err(Error::KeyMismatch { role: Role::Voter, id: "Alice".into() })
+        );
+        assert!(pool.votes_for("p1").is_empty());
+
+        // Alice's own vote takes the slot with a low nonce, and no evidence is raised
+        pool.submit(&registry, vote("p1", 1, Choice::Yes).sign(&alice).unwrap()).unwrap();
+        assert_eq!(pool.votes_for("p1")[0].vote.choice, Choice::Yes);
+        assert!(pool.evidence().is_empty());
+    }
+}
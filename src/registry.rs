@@ -0,0 +1,410 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::attestation::{AttestedVote, QuorumAttestedVote, QuorumPolicy, QuorumTime};
+use crate::blockchain::Blockchain;
+use crate::error::{Error, Result};
+use crate::payload::BlockPayload;
+use crate::voter::SignedVote;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Role {
+    Voter,
+    Validator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyAction {
+    Register,
+    Rotate,
+    Revoke,
+}
+
+/// A change to the registry, in the form it is written to the ledger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyEvent {
+    pub role: Role,
+    pub id: String,
+    pub action: KeyAction,
+    pub public_key: Option<String>, // base64, absent for revocations
+    pub at: DateTime<Utc>,
+}
+
+/// One key and the span it was valid for: `valid_from` inclusive,
+/// `valid_until` exclusive and open while the key is current.
+#[derive(Debug, Clone)]
+pub struct KeyRecord {
+    pub key: VerifyingKey,
+    pub valid_from: DateTime<Utc>,
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+impl KeyRecord {
+    pub fn is_active(&self) -> bool {
+        self.valid_until.is_none()
+    }
+
+    pub fn covers(&self, at: DateTime<Utc>) -> bool {
+        self.valid_from <= at && self.valid_until.is_none_or(|until| at < until)
+    }
+}
+
+/// Binds voter and validator ids to the keys allowed to sign for them.
+/// Rotated and revoked keys are kept, so a vote a validator received while
+/// a key was valid still verifies later.
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    records: HashMap<(Role, String), Vec<KeyRecord>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuilds the registry from the key changes recorded on a chain.
+    pub fn from_chain(chain: &Blockchain) -> Result<Self> {
+        let mut registry = Self::new();
+        for block in &chain.blocks {
+            if let BlockPayload::KeyChange(event) = block.payload()? {
+                registry.apply(&event)?;
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Every key the id has had, oldest first.
+    pub fn history(&self, role: Role, id: &str) -> &[KeyRecord] {
+        self.records.get(&(role, id.to_string())).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// The currently valid key, if the id is registered and not revoked.
+    pub fn key_for(&self, role: Role, id: &str) -> Option<&VerifyingKey> {
+        self.history(role, id).last().filter(|r| r.is_active()).map(|r| &r.key)
+    }
+
+    /// The key that was valid for the id at `at`.
+    pub fn key_at(&self, role: Role, id: &str, at: DateTime<Utc>) -> Option<&VerifyingKey> {
+        self.history(role, id).iter().rev().find(|r| r.covers(at)).map(|r| &r.key)
+    }
+
+    pub fn register(&mut self, role: Role, id: &str, key: VerifyingKey, now: DateTime<Utc>) -> Result<KeyEvent> {
+        self.apply_new(KeyEvent {
+            role,
+            id: id.to_string(),
+            action: KeyAction::Register,
+            public_key: Some(BASE64.encode(key.as_bytes())),
+            at: now,
+        })
+    }
+
+    pub fn rotate(&mut self, role: Role, id: &str, key: VerifyingKey, now: DateTime<Utc>) -> Result<KeyEvent> {
+        self.apply_new(KeyEvent {
+            role,
+            id: id.to_string(),
+            action: KeyAction::Rotate,
+            public_key: Some(BASE64.encode(key.as_bytes())),
+            at: now,
+        })
+    }
+
+    pub fn revoke(&mut self, role: Role, id: &str, now: DateTime<Utc>) -> Result<KeyEvent> {
+        self.apply_new(KeyEvent {
+            role,
+            id: id.to_string(),
+            action: KeyAction::Revoke,
+            public_key: None,
+            at: now,
+        })
+    }
+
+    fn apply_new(&mut self, event: KeyEvent) -> Result<KeyEvent> {
+        self.apply(&event)?;
+        Ok(event)
+    }
+
+    pub fn apply(&mut self, event: &KeyEvent) -> Result<()> {
+        let active = self.key_for(event.role, &event.id).is_some();
+        let key = match event.action {
+            KeyAction::Register if active => {
+                return Err(Error::AlreadyRegistered { role: event.role, id: event.id.clone() });
+            }
+            KeyAction::Rotate | KeyAction::Revoke if !active => {
+                return Err(Error::UnknownIdentity { role: event.role, id: event.id.clone() });
+            }
+            KeyAction::Register | KeyAction::Rotate => Some(decode_key(event)?),
+            KeyAction::Revoke => None,
+        };
+
+        let history = self.records.entry((event.role, event.id.clone())).or_default();
+        if let Some(current) = history.last_mut().filter(|r| r.is_active()) {
+            current.valid_until = Some(event.at);
+        }
+        if let Some(key) = key {
+            history.push(KeyRecord { key, valid_from: event.at, valid_until: None });
+        }
+        Ok(())
+    }
+
+    /// Verifies a vote against the currently registered keys: the voter must
+    /// be registered under the key embedded in the vote, the named validator
+    /// must be registered, and the signature must check out. `vote_time` is
+    /// the voter's own claim, so it never selects an older key.
+    pub fn verify_vote(&self, vote: &SignedVote) -> Result<()> {
+        self.verify_vote_with(vote, |role, id| self.key_for(role, id))
+    }
+
+    /// Like [`verify_vote`](Self::verify_vote), but against the keys that were
+    /// valid at `at`, a time the voter does not control such as a receipt.
+    fn verify_vote_at(&self, vote: &SignedVote, at: DateTime<Utc>) -> Result<()> {
+        self.verify_vote_with(vote, |role, id| self.key_at(role, id, at))
+    }
+
+    fn verify_vote_with<'a>(
+        &'a self,
+        vote: &SignedVote,
+        key: impl Fn(Role, &str) -> Option<&'a VerifyingKey>,
+    ) -> Result<()> {
+        let voter_id = &vote.vote.voter_id;
+        let voter_key = key(Role::Voter, voter_id)
+            .ok_or_else(|| Error::UnknownIdentity { role: Role::Voter, id: voter_id.clone() })?;
+        if voter_key != &vote.public_key {
+            return Err(Error::KeyMismatch { role: Role::Voter, id: voter_id.clone() });
+        }
+        if key(Role::Validator, &vote.vote.validator_id).is_none() {
+            return Err(Error::UnknownIdentity {
+                role: Role::Validator,
+                id: vote.vote.validator_id.clone(),
+            });
+        }
+        vote.ensure_valid()
+    }
+
+    /// The vote and the attestation are checked against the keys valid when
+    /// the validator received the vote; the attestation must be signed with
+    /// the named validator's key.
+    pub fn verify_attested(&self, vote: &AttestedVote) -> Result<()> {
+        let received_at = vote.attestation.received_at;
+        self.verify_vote_at(&vote.vote, received_at)?;
+        let validator_id = &vote.attestation.validator_id;
+        let key = self
+            .key_at(Role::Validator, validator_id, received_at)
+            .ok_or_else(|| Error::UnknownIdentity { role: Role::Validator, id: validator_id.clone() })?;
+        if key != &vote.attestation.public_key {
+            return Err(Error::KeyMismatch { role: Role::Validator, id: validator_id.clone() });
+        }
+        vote.ensure_valid()
+    }
+
+    /// The quorum time from the receipts of registered validators, then the
+    /// vote is checked against the keys valid at that time. Receipts from
+    /// unknown ids or under other keys are skipped; falling short of `policy`
+    /// is an error.
+    pub fn verify_quorum(&self, vote: &QuorumAttestedVote, policy: &QuorumPolicy) -> Result<QuorumTime> {
+        let quorum = vote.quorum_time(self, policy)?;
+        self.verify_vote_at(&vote.vote, quorum.time)?;
+        Ok(quorum)
+    }
+}
+
+fn decode_key(event: &KeyEvent) -> Result<VerifyingKey> {
+    let invalid = || Error::InvalidKey { role: event.role, id: event.id.clone() };
+    let bytes = BASE64.decode(event.public_key.as_deref().ok_or_else(invalid)?).map_err(|_| invalid())?;
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| invalid())?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::Attestation;
+    use crate::voter::{Choice, Vote};
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+    use rust_decimal_macros::dec;
+
+    fn signed(voter_id: &str, key: &SigningKey) -> SignedVote {
+        Vote {
+            voter_id: voter_id.to_string(),
+            validator_id: "Val1".to_string(),
+            vote_time: Utc::now(),
+            vote_weight: dec!(1.0),
+            choice: Choice::Yes,
+            proposal_id: "p1".into(),
+            nonce: 0,
+        }
+        .sign(key)
+        .unwrap()
+    }
+
+    fn registry_with(voter: &str, key: &SigningKey) -> Registry {
+        let mut registry = Registry::new();
+        let now = Utc::now();
+        registry.register(Role::Voter, voter, key.verifying_key(), now).unwrap();
+        registry
+            .register(Role::Validator, "Val1", SigningKey::generate(&mut OsRng).verifying_key(), now)
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_registered_key_verifies() {
+        let alice = SigningKey::generate(&mut OsRng);
+        let registry = registry_with("Alice", &alice);
+        assert!(registry.verify_vote(&signed("Alice", &alice)).is_ok());
+    }
+
+    #[test]
+    fn test_impersonation_is_rejected() {
+        let alice = SigningKey::generate(&mut OsRng);
+        let mallory = SigningKey::generate(&mut OsRng);
+        let registry = registry_with("Alice", &alice);
+
+        // validly signed, but with a key that isn't Alice's
+        let forged = signed("Alice", &mallory);
+        assert!(forged.verify());
+        assert_eq!(
+            registry.verify_vote(&forged),
+            Err(Error::KeyMismatch { role: Role::Voter, id: "Alice".to_string() })
+        );
+        assert_eq!(
+            registry.verify_vote(&signed("Bob", &mallory)),
+            Err(Error::UnknownIdentity { role: Role::Voter, id: "Bob".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_attestation_needs_registered_validator_key() {
+        let alice = SigningKey::generate(&mut OsRng);
+        let val1 = SigningKey::generate(&mut OsRng);
+        let mut registry = Registry::new();
+        registry.register(Role::Voter, "Alice", alice.verifying_key(), Utc::now()).unwrap();
+        registry.register(Role::Validator, "Val1", val1.verifying_key(), Utc::now()).unwrap();
+
+        let vote = signed("Alice", &alice);
+        let attested = AttestedVote::attest(vote.clone(), &val1, Utc::now()).unwrap();
+        assert!(registry.verify_attested(&attested).is_ok());
+
+        // a self-issued receipt under the validator's name
+        let forged = AttestedVote::attest(vote, &alice, Utc::now()).unwrap();
+        assert_eq!(
+            registry.verify_attested(&forged),
+            Err(Error::KeyMismatch { role: Role::Validator, id: "Val1".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_quorum_receipts_need_registered_validators() {
+        let alice = SigningKey::generate(&mut OsRng);
+        let val1 = SigningKey::generate(&mut OsRng);
+        let mut registry = Registry::new();
+        registry.register(Role::Voter, "Alice", alice.verifying_key(), Utc::now()).unwrap();
+        registry.register(Role::Validator, "Val1", val1.verifying_key(), Utc::now()).unwrap();
+
+        let policy = QuorumPolicy { min_attestations: 1, ..QuorumPolicy::default() };
+        let mut vote = QuorumAttestedVote::new(signed("Alice", &alice));
+        // an unknown validator's receipt is skipped rather than failing the vote
+        vote.add(Attestation::sign(&vote.vote, "Val9", &val1, Utc::now()).unwrap());
+        vote.add(Attestation::sign(&vote.vote, "Val1", &val1, Utc::now()).unwrap());
+        let quorum = registry.verify_quorum(&vote, &policy).unwrap();
+        assert_eq!(quorum.offsets.len(), 1);
+        assert_eq!(quorum.offsets[0].0, "Val1");
+
+        let strict = QuorumPolicy { min_attestations: 2, ..policy };
+        assert!(matches!(
+            registry.verify_quorum(&vote, &strict),
+            Err(Error::NoTimestampQuorum { attestations: 1, required: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_rotation_and_revocation() {
+        let old = SigningKey::generate(&mut OsRng);
+        let new = SigningKey::generate(&mut OsRng);
+        let mut registry = registry_with("Alice", &old);
+        let now = Utc::now();
+
+        assert!(registry.register(Role::Voter, "Alice", new.verifying_key(), now).is_err());
+
+        registry.rotate(Role::Voter, "Alice", new.verifying_key(), now).unwrap();
+        assert!(registry.verify_vote(&signed("Alice", &old)).is_err());
+        assert!(registry.verify_vote(&signed("Alice", &new)).is_ok());
+
+        registry.revoke(Role::Voter, "Alice", now).unwrap();
+        assert!(registry.key_for(Role::Voter, "Alice").is_none());
+        assert!(registry.verify_vote(&signed("Alice", &new)).is_err());
+        assert!(registry.revoke(Role::Voter, "Alice", now).is_err());
+
+        // a revoked id can be registered again
+        registry.register(Role::Voter, "Alice", old.verifying_key(), now).unwrap();
+        assert!(registry.verify_vote(&signed("Alice", &old)).is_ok());
+    }
+
+    #[test]
+    fn test_backdated_votes_need_a_current_key() {
+        let old = SigningKey::generate(&mut OsRng);
+        let new = SigningKey::generate(&mut OsRng);
+        let val1 = SigningKey::generate(&mut OsRng);
+        let start = Utc::now();
+        let mut registry = Registry::new();
+        registry.register(Role::Voter, "Alice", old.verifying_key(), start).unwrap();
+        registry.register(Role::Validator, "Val1", val1.verifying_key(), start).unwrap();
+
+        let mut early = signed("Alice", &old).vote;
+        early.vote_time = start + chrono::Duration::seconds(10);
+        let early = early.sign(&old).unwrap();
+        let received = AttestedVote::attest(early.clone(), &val1, start + chrono::Duration::seconds(11)).unwrap();
+
+        let rotated_at = start + chrono::Duration::seconds(60);
+        registry.rotate(Role::Voter, "Alice", new.verifying_key(), rotated_at).unwrap();
+
+        // received before the rotation, so the receipt still verifies
+        assert!(registry.verify_attested(&received).is_ok());
+        assert_eq!(
+            registry.verify_vote(&early),
+            Err(Error::KeyMismatch { role: Role::Voter, id: "Alice".to_string() })
+        );
+        assert!(registry.verify_vote(&early.vote.sign(&new).unwrap()).is_ok());
+
+        // a revoked key backdating a vote into its old validity window
+        registry.revoke(Role::Voter, "Alice", rotated_at + chrono::Duration::seconds(60)).unwrap();
+        let backdated = early.vote.sign(&new).unwrap();
+        assert_eq!(
+            registry.verify_vote(&backdated),
+            Err(Error::UnknownIdentity { role: Role::Voter, id: "Alice".to_string() })
+        );
+        let late_receipt = AttestedVote::attest(backdated, &val1, Utc::now() + chrono::Duration::minutes(5)).unwrap();
+        assert!(registry.verify_attested(&late_receipt).is_err());
+
+        let history = registry.history(Role::Voter, "Alice");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].valid_until, Some(rotated_at));
+        assert_eq!(registry.key_at(Role::Voter, "Alice", start - chrono::Duration::seconds(1)), None);
+    }
+
+    #[test]
+    fn test_registry_replays_from_chain() {
+        let alice = SigningKey::generate(&mut OsRng);
+        let rotated = SigningKey::generate(&mut OsRng);
+        let mut registry = Registry::new();
+        let mut chain = Blockchain::new();
+        let now = Utc::now();
+
+        let events = vec![
+            registry.register(Role::Voter, "Alice", alice.verifying_key(), now).unwrap(),
+            registry.register(Role::Validator, "Val1", alice.verifying_key(), now).unwrap(),
+            registry.rotate(Role::Voter, "Alice", rotated.verifying_key(), now).unwrap(),
+        ];
+        for event in events {
+            chain.add_payload(&BlockPayload::KeyChange(event));
+        }
+
+        let replayed = Registry::from_chain(&chain).unwrap();
+        assert_eq!(replayed.key_for(Role::Voter, "Alice"), Some(&rotated.verifying_key()));
+        assert!(replayed.verify_vote(&signed("Alice", &rotated)).is_ok());
+    }
+}
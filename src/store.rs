@@ -0,0 +1,418 @@
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::blockchain::{Block, Blockchain};
+use crate::clock::Clock;
+use crate::error::{Error, Result};
+
+// Record layout: body length u32 BE | checksum of the length | checksum of the body | body.
+// Checksums are the first 4 bytes of sha256; the body is the JSON encoding of a Block.
+// The length has its own checksum so a damaged length is caught before it is trusted.
+const RECORD_HEADER_LEN: usize = 12;
+pub const DEFAULT_SEGMENT_BYTES: u64 = 64 * 1024 * 1024;
+
+fn checksum(body: &[u8]) -> [u8; 4] {
+    let digest = Sha256::digest(body);
+    [digest[0], digest[1], digest[2], digest[3]]
+}
+
+fn segment_name(number: u64) -> String {
+    format!("segment-{:08}.log", number)
+}
+
+fn segment_number(path: &Path) -> Option<u64> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("segment-")?
+        .strip_suffix(".log")?
+        .parse()
+        .ok()
+}
+
+enum Decoded {
+    Block(Block, usize), // block and the offset just past its record
+    Torn,                // incomplete record at the end of the data
+    Corrupt,
+}
+
+fn decode_record(buf: &[u8], offset: usize) -> Decoded {
+    let rest = &buf[offset..];
+    if rest.len() < RECORD_HEADER_LEN {
+        return Decoded::Torn;
+    }
+    if rest[4..8] != checksum(&rest[0..4]) {
+        return Decoded::Corrupt;
+    }
+    let len = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+    let end = RECORD_HEADER_LEN + len;
+    // with the length verified, a short body runs to the end of the data:
+    // the final record, cut short by a crash
+    if rest.len() < end {
+        return Decoded::Torn;
+    }
+    // a complete record that fails its checksum was damaged after it was written
+    let body = &rest[RECORD_HEADER_LEN..end];
+    if rest[8..12] != checksum(body) {
+        return Decoded::Corrupt;
+    }
+    match serde_json::from_slice(body) {
+        Ok(block) => Decoded::Block(block, offset + end),
+        Err(_) => Decoded::Corrupt,
+    }
+}
+
+/// Append-only, file-backed block store. Blocks are written to numbered
+/// segment files as length-prefixed, checksummed records.
+pub struct LedgerStore {
+    dir: PathBuf,
+    segment_bytes: u64,
+    segments: Vec<u64>,
+    current: File,
+    current_len: u64,
+}
+
+impl LedgerStore {
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_segment_size(dir, DEFAULT_SEGMENT_BYTES)
+    }
+
+    /// Opens (or creates) the store and recovers it: a torn record at the end
+    /// of the last segment is truncated away, any other damage is an error.
+    pub fn open_with_segment_size(dir: impl AsRef<Path>, segment_bytes: u64) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut segments: Vec<u64> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().and_then(|e| segment_number(&e.path())))
+            .collect();
+        segments.sort_unstable();
+        if segments.is_empty() {
+            segments.push(0);
+        }
+
+        for (i, number) in segments.iter().enumerate() {
+            let path = dir.join(segment_name(*number));
+            if !path.exists() {
+                continue;
+            }
+            let buf = fs::read(&path)?;
+            let mut offset = 0;
+            while offset < buf.len() {
+                match decode_record(&buf, offset) {
+                    Decoded::Block(_, next) => offset = next,
+                    Decoded::Torn if i + 1 == segments.len() => {
+                        let file = OpenOptions::new().write(true).open(&path)?;
+                        file.set_len(offset as u64)?;
+                        file.sync_all()?;
+                        break;
+                    }
+                    _ => return Err(Error::CorruptLedger { segment: *number, offset: offset as u64 }),
+                }
+            }
+        }
+
+        let last = *segments.last().unwrap();
+        let current = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(segment_name(last)))?;
+        let current_len = current.metadata()?.len();
+        sync_dir(&dir)?;
+
+        Ok(Self {
+            dir,
+            segment_bytes,
+            segments,
+            current,
+            current_len,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Durably appends a block; returns once the record is synced to disk.
+    /// A failed write is truncated away so the segment never ends in a
+    /// partial record.
+    pub fn append(&mut self, block: &Block) -> Result<()> {
+        let body = serde_json::to_vec(block).map_err(|e| Error::Io(e.to_string()))?;
+        let len = u32::try_from(body.len())
+            .map_err(|_| Error::Io(format!("block of {} bytes is too large", body.len())))?;
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + body.len());
+        record.extend_from_slice(&len.to_be_bytes());
+        record.extend_from_slice(&checksum(&len.to_be_bytes()));
+        record.extend_from_slice(&checksum(&body));
+        record.extend_from_slice(&body);
+
+        if self.current_len > 0 && self.current_len + record.len() as u64 > self.segment_bytes {
+            self.roll_segment()?;
+        }
+        if let Err(err) = self.current.write_all(&record).and_then(|_| self.current.sync_data()) {
+            // best effort; recovery on open handles whatever is left
+            let _ = self.current.set_len(self.current_len);
+            return Err(err.into());
+        }
+        self.current_len += record.len() as u64;
+        Ok(())
+    }
+
+    fn roll_segment(&mut self) -> Result<()> {
+        let next = self.segments.last().unwrap() + 1;
+        self.current = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(segment_name(next)))?;
+        sync_dir(&self.dir)?;
+        self.segments.push(next);
+        self.current_len = 0;
+        Ok(())
+    }
+
+    /// Iterates every stored block in append order.
+    pub fn iter(&self) -> LedgerIter {
+        LedgerIter {
+            dir: self.dir.clone(),
+            segments: self.segments.clone(),
+            next_segment: 0,
+            segment: 0,
+            buf: Vec::new(),
+            offset: 0,
+            failed: false,
+        }
+    }
+
+    /// Loads and validates the whole chain. An empty store is seeded with a
+    /// genesis block from `clock`.
+    pub fn load_chain(&mut self, clock: Arc<dyn Clock>) -> Result<Blockchain> {
+        let blocks = self.iter().collect::<Result<Vec<_>>>()?;
+        if blocks.is_empty() {
+            let chain = Blockchain::with_clock(clock);
+            self.append(&chain.blocks[0])?;
+            return Ok(chain);
+        }
+        Blockchain::from_blocks(blocks, clock)
+    }
+}
+
+pub struct LedgerIter {
+    dir: PathBuf,
+    segments: Vec<u64>,
+    next_segment: usize,
+    segment: u64,
+    buf: Vec<u8>,
+    offset: usize,
+    failed: bool,
+}
+
+impl Iterator for LedgerIter {
+    type Item = Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        while self.offset >= self.buf.len() {
+            let number = *self.segments.get(self.next_segment)?;
+            self.next_segment += 1;
+            self.segment = number;
+            self.offset = 0;
+            self.buf = match fs::read(self.dir.join(segment_name(number))) {
+                Ok(buf) => buf,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e.into()));
+                }
+            };
+        }
+        match decode_record(&self.buf, self.offset) {
+            Decoded::Block(block, next) => {
+                self.offset = next;
+                Some(Ok(block))
+            }
+            _ => {
+                self.failed = true;
+                Some(Err(Error::CorruptLedger { segment: self.segment, offset: self.offset as u64 }))
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use chrono::Utc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "chronovote-{}-{}-{}",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn clock() -> Arc<dyn Clock> {
+        Arc::new(SimulatedClock::new(Utc::now()))
+    }
+
+    fn build_chain(store: &mut LedgerStore, blocks: usize) -> Blockchain {
+        let mut chain = store.load_chain(clock()).unwrap();
+        for i in 0..blocks {
+            chain.add_blocks(format!("block {}", i));
+            store.append(chain.blocks.last().unwrap()).unwrap();
+        }
+        chain
+    }
+
+    #[test]
+    fn test_chain_survives_reopen() {
+        let dir = temp_dir("reopen");
+        let chain = {
+            let mut store = LedgerStore::open(&dir).unwrap();
+            build_chain(&mut store, 3)
+        };
+
+        let mut store = LedgerStore::open(&dir).unwrap();
+        let loaded = store.load_chain(clock()).unwrap();
+        assert_eq!(loaded.blocks.len(), 4);
+        assert_eq!(loaded.blocks.last().unwrap().hash, chain.blocks.last().unwrap().hash);
+        assert!(loaded.is_valid());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_torn_final_write_is_truncated() {
+        let dir = temp_dir("torn");
+        {
+            let mut store = LedgerStore::open(&dir).unwrap();
+            build_chain(&mut store, 2);
+        }
+        let path = dir.join(segment_name(0));
+        let intact = fs::metadata(&path).unwrap().len();
+        {
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(&[0, 0, 1, 0, 9, 9]).unwrap();
+        }
+
+        let mut store = LedgerStore::open(&dir).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), intact);
+        assert_eq!(store.load_chain(clock()).unwrap().blocks.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_corruption_before_tail_is_rejected() {
+        let dir = temp_dir("corrupt");
+        {
+            let mut store = LedgerStore::open(&dir).unwrap();
+            build_chain(&mut store, 2);
+        }
+        let path = dir.join(segment_name(0));
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[RECORD_HEADER_LEN + 2] ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        assert!(matches!(
+            LedgerStore::open(&dir),
+            Err(Error::CorruptLedger { segment: 0, offset: 0 })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_damaged_final_record_is_rejected() {
+        let dir = temp_dir("damaged-tail");
+        {
+            let mut store = LedgerStore::open(&dir).unwrap();
+            build_chain(&mut store, 2);
+        }
+        let path = dir.join(segment_name(0));
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 2;
+        bytes[last] ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        // complete but failing its checksum: corruption, not a torn write
+        assert!(matches!(LedgerStore::open(&dir), Err(Error::CorruptLedger { segment: 0, .. })));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_damaged_length_is_not_mistaken_for_a_torn_write() {
+        let dir = temp_dir("damaged-length");
+        {
+            let mut store = LedgerStore::open(&dir).unwrap();
+            build_chain(&mut store, 2);
+        }
+        let path = dir.join(segment_name(0));
+        let mut bytes = fs::read(&path).unwrap();
+        let intact = bytes.len() as u64;
+        // point the second record's length past the end of the segment
+        let first_len = u32::from_be_bytes(bytes[0..4].try_into().unwrap()) as usize;
+        let middle = RECORD_HEADER_LEN + first_len;
+        bytes[middle] = 0x7f;
+        fs::write(&path, bytes).unwrap();
+
+        assert!(matches!(
+            LedgerStore::open(&dir),
+            Err(Error::CorruptLedger { segment: 0, offset }) if offset == middle as u64
+        ));
+        // nothing after the damaged record was truncated away
+        assert_eq!(fs::metadata(&path).unwrap().len(), intact);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_segments_roll_over() {
+        let dir = temp_dir("segments");
+        {
+            let mut store = LedgerStore::open_with_segment_size(&dir, 512).unwrap();
+            build_chain(&mut store, 6);
+            assert!(store.segment_count() > 1);
+        }
+
+        let store = LedgerStore::open_with_segment_size(&dir, 512).unwrap();
+        let indices: Vec<usize> = store.iter().map(|b| b.unwrap().index).collect();
+        assert_eq!(indices, (0..7).collect::<Vec<_>>());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tampered_chain_fails_validation_on_load() {
+        let dir = temp_dir("tampered");
+        {
+            let mut store = LedgerStore::open(&dir).unwrap();
+            let mut chain = build_chain(&mut store, 1);
+            chain.blocks[1].data = "forged".to_string();
+            store.append(&chain.blocks[1]).unwrap();
+        }
+
+        let mut store = LedgerStore::open(&dir).unwrap();
+        assert!(matches!(store.load_chain(clock()), Err(Error::InvalidChain { .. })));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use std::collections::BTreeMap;
+
+use crate::error::Result;
+use crate::policy::{ThresholdPolicy, ThresholdSource};
+use crate::threshold::{ThresholdEmergency, ThresholdModel};
+use crate::voter::Choice;
+use crate::weight::{DecayBasis, WeightedVote};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TallyStatus {
+    Passed,  // window closed with the weighted yes share at or above the threshold
+    Failed,  // window closed short of it
+    Pending, // window still open; `margin` shows where it would land now
+}
+
+#[derive(Debug, Clone)]
+pub struct TallyOutcome {
+    pub status: TallyStatus,
+    pub evaluated_at: DateTime<Utc>,
+    pub yes_weight: Decimal,
+    pub no_weight: Decimal,
+    pub abstain_weight: Decimal,
+    pub option_weights: BTreeMap<u32, Decimal>,
+    /// yes / (yes + no); abstentions and multi-option choices are ignored
+    pub yes_share: Decimal,
+    pub threshold: f64,
+    /// Which part of the threshold policy set `threshold`
+    pub threshold_source: ThresholdSource,
+    /// yes_share minus threshold, negative while short of passing
+    pub margin: Decimal,
+}
+
+/// Decay-weighted tally of a single proposal.
+#[derive(Debug, Clone)]
+pub struct Tally {
+    pub vote_start: DateTime<Utc>,
+    pub vote_end: DateTime<Utc>,
+    pub policy: ThresholdPolicy,
+    /// Voters who could take part; participation is 100% when unknown
+    pub eligible_voters: Option<usize>,
+    pub decay_basis: DecayBasis,
+    pub votes: Vec<(Choice, WeightedVote)>,
+}
+
+impl Tally {
+    /// A tally under the base curve alone; see [`with_policy`](Self::with_policy).
+    pub fn new(
+        vote_start: DateTime<Utc>,
+        vote_end: DateTime<Utc>,
+        threshold_model: ThresholdModel,
+        override_mode: Option<ThresholdEmergency>,
+    ) -> Result<Self> {
+        let mut policy = ThresholdPolicy::new(threshold_model);
+        policy.emergency = override_mode;
+        Self::with_policy(vote_start, vote_end, policy)
+    }
+
+    /// Rejects a policy that fails [`ThresholdPolicy::validate`].
+    pub fn with_policy(vote_start: DateTime<Utc>, vote_end: DateTime<Utc>, policy: ThresholdPolicy) -> Result<Self> {
+        policy.validate()?;
+        Ok(Self {
+            vote_start,
+            vote_end,
+            policy,
+            eligible_voters: None,
+            decay_basis: DecayBasis::default(),
+            votes: Vec::new(),
+        })
+    }
+
+    pub fn add_vote(&mut self, choice: Choice, vote: WeightedVote) {
+        self.votes.push((choice, vote));
+    }
+
+    /// Weighs the votes cast by `now`, ignoring any stamped after `vote_end`;
+    /// participation counts the same votes. The proposal is only decided once
+    /// the window has closed, against the weights and threshold at `vote_end`,
+    /// so a decision never changes on later evaluations.
+    pub fn evaluate(&self, now: DateTime<Utc>) -> TallyOutcome {
+        let at = now.min(self.vote_end);
+        let mut yes_weight = Decimal::ZERO;
+        let mut no_weight = Decimal::ZERO;
+        let mut abstain_weight = Decimal::ZERO;
+        let mut option_weights = BTreeMap::new();
+        let mut yes_votes = 0;
+
+        let cast = self.votes.iter().filter(|(_, vote)| vote.vote_time <= at);
+        let mut cast_votes = 0;
+        for (choice, vote) in cast {
+            cast_votes += 1;
+            let weight = vote.effective_weight_with(self.decay_basis, self.vote_start, at);
+            match choice {
+                Choice::Yes => {
+                    yes_weight += weight;
+                    yes_votes += 1;
+                }
+                Choice::No => no_weight += weight,
+                Choice::Abstain => abstain_weight += weight,
+                Choice::Option(idx) => *option_weights.entry(*idx).or_insert(Decimal::ZERO) += weight,
+            }
+        }
+
+        let decisive = yes_weight + no_weight;
+        let yes_share = if decisive.is_zero() {
+            Decimal::ZERO
+        } else {
+            yes_weight / decisive
+        };
+
+        let participation = match self.eligible_voters {
+            Some(0) | None => 1.0,
+            Some(eligible) => cast_votes as f64 / eligible as f64,
+        };
+        let decision = self.policy.evaluate(self.vote_start, at, participation);
+        let threshold = decision.threshold;
+        let margin = yes_share - Decimal::from_f64(threshold).unwrap_or(Decimal::ONE);
+
+        let status = if now < self.vote_end {
+            TallyStatus::Pending
+        } else if !decisive.is_zero() && margin >= Decimal::ZERO && yes_votes >= decision.min_yes_votes {
+            TallyStatus::Passed
+        } else {
+            TallyStatus::Failed
+        };
+
+        TallyOutcome {
+            status,
+            evaluated_at: at,
+            yes_weight,
+            no_weight,
+            abstain_weight,
+            option_weights,
+            yes_share,
+            threshold,
+            threshold_source: decision.source,
+            margin,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decay::DecayModel;
+    use chrono::Duration;
+    use rust_decimal_macros::dec;
+
+    fn weighted(voter_id: &str, reputation_bonus: Decimal) -> WeightedVote {
+        WeightedVote {
+            voter_id: voter_id.to_string(),
+            vote_time: start(),
+            orig_weight: dec!(1.0),
+            decay_model: DecayModel::Linear(0.0),
+            reputation_bonus,
+        }
+    }
+
+    fn start() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    #[test]
+    fn test_weighted_yes_share_passes() {
+        let start = start();
+        let end = start + Duration::minutes(30);
+        let mut tally = Tally::new(start, end, ThresholdModel::Linear(0.0), None).unwrap();
+        tally.add_vote(Choice::Yes, weighted("alice", dec!(0.0)));
+        tally.add_vote(Choice::Yes, weighted("bob", dec!(0.0)));
+        tally.add_vote(Choice::No, weighted("carol", dec!(0.0)));
+        tally.add_vote(Choice::Abstain, weighted("dave", dec!(0.0)));
+
+        assert_eq!(tally.evaluate(start).status, TallyStatus::Pending);
+        let outcome = tally.evaluate(end);
+        assert_eq!(outcome.status, TallyStatus::Passed);
+        assert_eq!(outcome.yes_weight, dec!(2.0));
+        assert_eq!(outcome.no_weight, dec!(1.0));
+        assert_eq!(outcome.abstain_weight, dec!(1.0));
+        assert!((outcome.threshold - 0.51).abs() < 0.001);
+        assert!(outcome.margin > Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_reputation_changes_outcome() {
+        let start = start();
+        let end = start + Duration::minutes(30);
+        let mut tally = Tally::new(start, end, ThresholdModel::Linear(0.0), None).unwrap();
+        tally.add_vote(Choice::Yes, weighted("alice", dec!(0.0)));
+        tally.add_vote(Choice::No, weighted("bob", dec!(0.0)));
+        assert_eq!(tally.evaluate(end).status, TallyStatus::Failed);
+
+        tally.votes[0].1.reputation_bonus = dec!(0.5);
+        assert_eq!(tally.evaluate(end).status, TallyStatus::Passed);
+    }
+
+    #[test]
+    fn test_escalated_threshold_fails_after_window() {
+        let start = start();
+        let end = start + Duration::minutes(30);
+        let mut tally = Tally::new(start, end, ThresholdModel::Linear(0.01), None).unwrap();
+        for i in 0..6 {
+            tally.add_vote(Choice::Yes, weighted(&format!("yes{}", i), dec!(0.0)));
+        }
+        for i in 0..4 {
+            tally.add_vote(Choice::No, weighted(&format!("no{}", i), dec!(0.0)));
+        }
+
+        // 60% yes clears 0.51 at the start but not 0.81 at the end
+        let early = tally.evaluate(start);
+        assert_eq!(early.status, TallyStatus::Pending);
+        assert!(early.margin > Decimal::ZERO);
+        let pending = tally.evaluate(start + Duration::minutes(20));
+        assert_eq!(pending.status, TallyStatus::Pending);
+        assert!(pending.margin < Decimal::ZERO);
+        assert_eq!(tally.evaluate(end).status, TallyStatus::Failed);
+    }
+
+    #[test]
+    fn test_decision_is_final_after_window() {
+        let start = start();
+        let end = start + Duration::minutes(30);
+        let mut tally = Tally::new(start, end, ThresholdModel::Linear(0.0), None).unwrap();
+        tally.decay_basis = DecayBasis::Ongoing;
+        // yes decays to 1.28 by the close, and would fall below no long after it
+        let mut decaying = weighted("alice", dec!(0.0));
+        decaying.orig_weight = dec!(2.0);
+        decaying.decay_model = DecayModel::Linear(0.0002);
+        tally.add_vote(Choice::Yes, decaying);
+        tally.add_vote(Choice::No, weighted("bob", dec!(0.0)));
+
+        let closed = tally.evaluate(end);
+        let later = tally.evaluate(end + Duration::days(30));
+        assert_eq!(closed.status, TallyStatus::Passed);
+        assert_eq!(later.status, TallyStatus::Passed);
+        assert_eq!(later.evaluated_at, end);
+        assert_eq!(later.margin, closed.margin);
+    }
+
+    #[test]
+    fn test_votes_after_now_are_skipped() {
+        let start = start();
+        let end = start + Duration::minutes(30);
+        let mut tally = Tally::new(start, end, ThresholdModel::Linear(0.0), None).unwrap();
+        tally.eligible_voters = Some(2);
+        tally.add_vote(Choice::Yes, weighted("alice", dec!(0.0)));
+        let mut future = weighted("bob", dec!(0.0));
+        future.vote_time = end + Duration::minutes(1);
+        tally.add_vote(Choice::No, future);
+
+        let outcome = tally.evaluate(end);
+        assert_eq!(outcome.no_weight, Decimal::ZERO);
+        assert_eq!(outcome.status, TallyStatus::Passed);
+
+        // stamped after the close, so it never counts and the decision stands
+        let later = tally.evaluate(end + Duration::minutes(1));
+        assert_eq!(later.no_weight, Decimal::ZERO);
+        assert_eq!(later.status, TallyStatus::Passed);
+        assert_eq!(later.margin, outcome.margin);
+    }
+
+    #[test]
+    fn test_empty_tally_never_passes() {
+        let start = start();
+        let tally = Tally::new(start, start + Duration::minutes(5), ThresholdModel::Linear(0.01), None).unwrap();
+        assert_eq!(tally.evaluate(start).status, TallyStatus::Pending);
+        assert_eq!(tally.evaluate(start + Duration::minutes(5)).status, TallyStatus::Failed);
+    }
+
+    #[test]
+    fn test_new_validates_the_base_curve() {
+        use crate::error::Error;
+        use crate::threshold::ThresholdFault;
+
+        let start = start();
+        assert_eq!(
+            Tally::new(start, start + Duration::minutes(5), ThresholdModel::Linear(-0.01), None).err(),
+            Some(Error::InvalidThreshold(ThresholdFault::DecreasingSlope(-0.01)))
+        );
+    }
+
+    #[test]
+    fn test_multi_option_weights() {
+        let start = start();
+        let mut tally = Tally::new(start, start + Duration::minutes(5), ThresholdModel::Linear(0.01), None).unwrap();
+        tally.add_vote(Choice::Option(0), weighted("alice", dec!(0.0)));
+        tally.add_vote(Choice::Option(1), weighted("bob", dec!(0.0)));
+        tally.add_vote(Choice::Option(1), weighted("carol", dec!(0.0)));
+
+        let outcome = tally.evaluate(start);
+        assert_eq!(outcome.option_weights.get(&0), Some(&dec!(1.0)));
+        assert_eq!(outcome.option_weights.get(&1), Some(&dec!(2.0)));
+    }
+
+    #[test]
+    fn test_policy_head_count_and_participation() {
+        use crate::threshold_prog::{ProgressionProfile, Proposaltype};
+
+        let start = start();
+        let mut policy = ThresholdPolicy::new(ThresholdModel::Linear(0.0));
+        policy.proposal_type = Some(Proposaltype::Normal);
+        let end = start + Duration::minutes(30);
+        let mut tally = Tally::with_policy(start, end, policy).unwrap();
+        for i in 0..4 {
+            tally.add_vote(Choice::Yes, weighted(&format!("yes{}", i), dec!(0.0)));
+        }
+
+        // a unanimous share is not enough below the five-vote minimum
+        assert_eq!(tally.evaluate(end).status, TallyStatus::Failed);
+        tally.add_vote(Choice::Yes, weighted("yes4", dec!(0.0)));
+        assert_eq!(tally.evaluate(end).status, TallyStatus::Passed);
+
+        tally.policy.profile = Some(ProgressionProfile::Adaptive);
+        tally.eligible_voters = Some(50);
+        let outcome = tally.evaluate(start);
+        assert_eq!(outcome.threshold_source, ThresholdSource::Profile);
+        assert!((outcome.threshold - 0.70).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_with_policy_rejects_bad_model() {
+        let start = start();
+        let policy = ThresholdPolicy::new(ThresholdModel::Exponential(f64::NAN));
+        assert!(Tally::with_policy(start, start + Duration::minutes(5), policy).is_err());
+    }
+}
@@ -1,10 +1,36 @@
 use chrono::{DateTime,Utc};
+use crate::error::{Error,Result};
 
+#[derive(Debug,Clone)]
 pub enum ThresholdModel{
     Exponential(f64),
     Linear(f64),
     Sigmoid {steepness: f64,midpoint:f64},
     StepFn(Vec<(u64,f64)>),
+    //same (minute, threshold) points as StepFn, linearly interpolated between them;
+    //starts from the floor at minute 0 unless the first point says otherwise
+    PiecewiseLinear(Vec<(u64,f64)>),
+}
+
+//how finely elapsed time is measured; model parameters stay per minute
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub enum Resolution{
+    Minute, //the original behaviour: thresholds only move on whole minutes
+    Second,
+    #[default]
+    Millisecond,
+}
+
+impl Resolution{
+    pub fn elapsed_minutes(&self,start_time:DateTime<Utc>,now:DateTime<Utc>)->f64{
+        let elapsed=now-start_time;
+        let minutes=match self{
+            Resolution::Minute=>elapsed.num_minutes() as f64,
+            Resolution::Second=>elapsed.num_seconds() as f64/60.0,
+            Resolution::Millisecond=>elapsed.num_milliseconds() as f64/60_000.0,
+        };
+        minutes.max(0.0)
+    }
 }
 
 // This enum represents the different types of thresholds that can be applied to emergency situations.
@@ -13,44 +39,244 @@ pub enum ThresholdEmergency{
     Emergency(f64),
 }
 
+//why a ThresholdModel was rejected; parameter names match the variant fields
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ThresholdFault{
+    NonFinite{parameter:&'static str},
+    OutOfRange{parameter:&'static str,value:f64},
+    UnsortedSteps{index:usize},      //steps[index] is not after steps[index-1]
+    StepOutOfRange{index:usize,value:f64}, //step values are shares, 0..=1
+    DecreasingStep{index:usize},     //only when a monotonic schedule is required; index 0 is below the floor
+    DecreasingSlope(f64),
+    InvalidBounds{floor:f64,ceiling:f64}, //need 0 < floor <= ceiling <= 1
+}
+
+fn fault(fault:ThresholdFault)->Error{
+    Error::InvalidThreshold(fault)
+}
+
+fn finite(parameter:&'static str,value:f64)->Result<()>{
+    if value.is_finite(){Ok(())}else{Err(fault(ThresholdFault::NonFinite{parameter}))}
+}
+
+impl ThresholdModel{
+    pub fn linear(slope:f64)->Result<Self>{
+        Self::checked(ThresholdModel::Linear(slope))
+    }
+
+    pub fn exponential(growth_rate:f64)->Result<Self>{
+        Self::checked(ThresholdModel::Exponential(growth_rate))
+    }
+
+    pub fn sigmoid(steepness:f64,midpoint:f64)->Result<Self>{
+        Self::checked(ThresholdModel::Sigmoid{steepness,midpoint})
+    }
+
+    pub fn step_fn(steps:Vec<(u64,f64)>)->Result<Self>{
+        Self::checked(ThresholdModel::StepFn(steps))
+    }
+
+    pub fn piecewise_linear(points:Vec<(u64,f64)>)->Result<Self>{
+        Self::checked(ThresholdModel::PiecewiseLinear(points))
+    }
+
+    fn checked(model:ThresholdModel)->Result<Self>{
+        model.validate()?;
+        Ok(model)
+    }
+
+    //rejects parameters that would otherwise only be noticed through clamping
+    pub fn validate(&self)->Result<()>{
+        match self{
+            ThresholdModel::Linear(slope)=>finite("slope",*slope),
+            ThresholdModel::Exponential(growth_rate)=>{
+                finite("growth_rate",*growth_rate)?;
+                if *growth_rate<0.0{
+                    return Err(fault(ThresholdFault::OutOfRange{parameter:"growth_rate",value:*growth_rate}));
+                }
+                Ok(())
+            }
+            ThresholdModel::Sigmoid{steepness,midpoint}=>{
+                finite("steepness",*steepness)?;
+                finite("midpoint",*midpoint)?;
+                if *steepness<=0.0{
+                    return Err(fault(ThresholdFault::OutOfRange{parameter:"steepness",value:*steepness}));
+                }
+                if *midpoint<0.0{
+                    return Err(fault(ThresholdFault::OutOfRange{parameter:"midpoint",value:*midpoint}));
+                }
+                Ok(())
+            }
+            ThresholdModel::StepFn(steps)|ThresholdModel::PiecewiseLinear(steps)=>{
+                for(index,(time,value)) in steps.iter().enumerate(){
+                    if !value.is_finite(){
+                        return Err(fault(ThresholdFault::NonFinite{parameter:"steps"}));
+                    }
+                    if !(0.0..=1.0).contains(value){
+                        return Err(fault(ThresholdFault::StepOutOfRange{index,value:*value}));
+                    }
+                    if index>0&&*time<=steps[index-1].0{
+                        return Err(fault(ThresholdFault::UnsortedSteps{index}));
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    //validate, and the threshold must never go down over time; the schedule
+    //starts at bounds.floor, so the first step may not be below it
+    pub fn validate_monotonic(&self,bounds:&ThresholdBounds)->Result<()>{
+        self.validate()?;
+        match self{
+            ThresholdModel::Linear(slope) if *slope<0.0=>Err(fault(ThresholdFault::DecreasingSlope(*slope))),
+            ThresholdModel::StepFn(steps)|ThresholdModel::PiecewiseLinear(steps)=>{
+                if steps.first().is_some_and(|first| first.1<bounds.floor){
+                    return Err(fault(ThresholdFault::DecreasingStep{index:0}));
+                }
+                match steps.windows(2).position(|pair| pair[1].1<pair[0].1){
+                    Some(i)=>Err(fault(ThresholdFault::DecreasingStep{index:i+1})),
+                    None=>Ok(()),
+                }
+            }
+            _=>Ok(()),
+        }
+    }
+}
+
+//defaults for ThresholdBounds
 pub const MIN_THRESHOLD:f64=0.51;
 pub const MAX_THRESHOLD:f64=0.90;
 
+//lowest and highest threshold a proposal can have, emergency override included;
+//every model starts from floor and is clamped to ceiling
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct ThresholdBounds{
+    pub floor:f64,
+    pub ceiling:f64,
+}
+
+impl Default for ThresholdBounds{
+    fn default()->Self{
+        ThresholdBounds{floor:MIN_THRESHOLD,ceiling:MAX_THRESHOLD}
+    }
+}
+
+impl ThresholdBounds{
+    pub fn new(floor:f64,ceiling:f64)->Result<Self>{
+        let bounds=ThresholdBounds{floor,ceiling};
+        bounds.validate()?;
+        Ok(bounds)
+    }
+
+    pub fn validate(&self)->Result<()>{
+        let ok=self.floor.is_finite()
+            &&self.ceiling.is_finite()
+            &&self.floor>0.0
+            &&self.floor<=self.ceiling
+            &&self.ceiling<=1.0;
+        if ok{
+            Ok(())
+        }else{
+            Err(fault(ThresholdFault::InvalidBounds{floor:self.floor,ceiling:self.ceiling}))
+        }
+    }
+
+    //floor first, then ceiling; unlike f64::clamp this never panics, so
+    //unvalidated bounds (inverted or NaN) only give a meaningless threshold.
+    //A NaN value stays NaN so it can still be caught downstream
+    pub fn clamp(&self,value:f64)->f64{
+        if value.is_nan(){
+            return value;
+        }
+        value.max(self.floor).min(self.ceiling)
+    }
+}
+
 pub fn threshold_at(
     start_time: DateTime<Utc>,
     now:DateTime<Utc>,
     model:&ThresholdModel,
     override_mode:Option<ThresholdEmergency>,
+)-> f64 {
+    threshold_at_resolution(start_time,now,model,override_mode,Resolution::default())
+}
+
+pub fn threshold_at_resolution(
+    start_time: DateTime<Utc>,
+    now:DateTime<Utc>,
+    model:&ThresholdModel,
+    override_mode:Option<ThresholdEmergency>,
+    resolution:Resolution,
+)-> f64 {
+    threshold_within(start_time,now,model,override_mode,resolution,&ThresholdBounds::default())
+}
+
+pub fn threshold_within(
+    start_time: DateTime<Utc>,
+    now:DateTime<Utc>,
+    model:&ThresholdModel,
+    override_mode:Option<ThresholdEmergency>,
+    resolution:Resolution,
+    bounds:&ThresholdBounds,
 )-> f64 {
     if let Some(ThresholdEmergency::Emergency(value))=override_mode{
-        return value.max(MIN_THRESHOLD).min(MAX_THRESHOLD)
+        return bounds.clamp(value)
     }
-    let elapsed_minutes=(now-start_time).num_minutes().max(0) as f64;
+    bounds.clamp(base_threshold(start_time,now,model,resolution,bounds))
+}
 
-    let base=match model{
+//the model's curve before it is clamped to bounds; the bounds still shape
+//the curve itself (its starting point, and the sigmoid's range)
+pub fn base_threshold(
+    start_time: DateTime<Utc>,
+    now:DateTime<Utc>,
+    model:&ThresholdModel,
+    resolution:Resolution,
+    bounds:&ThresholdBounds,
+)-> f64 {
+    let (floor,ceiling)=(bounds.floor,bounds.ceiling);
+    let elapsed_minutes=resolution.elapsed_minutes(start_time,now);
+
+    match model{
         ThresholdModel::Exponential(growth_rate)=>{
-            MIN_THRESHOLD*(1.0+growth_rate).powf(elapsed_minutes)
+            floor*(1.0+growth_rate).powf(elapsed_minutes)
         }
         ThresholdModel::Linear(slope)=>{
-            MIN_THRESHOLD+slope*elapsed_minutes
+            floor+slope*elapsed_minutes
         }
         ThresholdModel::Sigmoid{steepness,midpoint}=>{
             let x = elapsed_minutes;
-            MIN_THRESHOLD + (MAX_THRESHOLD - MIN_THRESHOLD)
+            floor + (ceiling - floor)
                 / (1.0 + (-steepness * (x - midpoint)).exp())
         }
         ThresholdModel::StepFn(steps)=>{
-              let mut threshold=MIN_THRESHOLD;
+              let mut threshold=floor;
               for(time,value) in steps.iter(){
                 if *time as f64<=elapsed_minutes{
-                    threshold=*value;
+                    threshold = *value;
                 }
             }
             threshold
 
         }
-    };
-    base.min(MAX_THRESHOLD).max(MIN_THRESHOLD)
+        ThresholdModel::PiecewiseLinear(points)=>interpolate(points,elapsed_minutes,floor),
+    }
+}
+
+fn interpolate(points:&[(u64,f64)],elapsed_minutes:f64,floor:f64)->f64{
+    let mut prev=(0.0,floor);
+    for(time,value) in points.iter(){
+        let time=*time as f64;
+        if elapsed_minutes<time{
+            if time<=prev.0{
+                return *value;
+            }
+            return prev.1+(value-prev.1)*(elapsed_minutes-prev.0)/(time-prev.0);
+        }
+        prev=(time,*value);
+    }
+    prev.1
 }
 
 #[cfg(test)]
@@ -127,4 +353,141 @@ mod tests {
             "Expected clamped max threshold"
         );
     }
+
+    #[test]
+    fn test_constructors_reject_bad_parameters() {
+        assert!(ThresholdModel::linear(0.01).is_ok());
+        assert_eq!(
+            ThresholdModel::linear(f64::NAN).unwrap_err(),
+            Error::InvalidThreshold(ThresholdFault::NonFinite { parameter: "slope" })
+        );
+        assert_eq!(
+            ThresholdModel::exponential(-0.1).unwrap_err(),
+            Error::InvalidThreshold(ThresholdFault::OutOfRange { parameter: "growth_rate", value: -0.1 })
+        );
+        assert_eq!(
+            ThresholdModel::sigmoid(0.0, 10.0).unwrap_err(),
+            Error::InvalidThreshold(ThresholdFault::OutOfRange { parameter: "steepness", value: 0.0 })
+        );
+        assert_eq!(
+            ThresholdModel::sigmoid(1.0, f64::INFINITY).unwrap_err(),
+            Error::InvalidThreshold(ThresholdFault::NonFinite { parameter: "midpoint" })
+        );
+    }
+
+    #[test]
+    fn test_step_fn_validation() {
+        assert!(ThresholdModel::step_fn(vec![(0, 0.51), (5, 0.6), (10, 0.75)]).is_ok());
+        assert_eq!(
+            ThresholdModel::step_fn(vec![(0, 0.51), (10, 0.6), (5, 0.75)]).unwrap_err(),
+            Error::InvalidThreshold(ThresholdFault::UnsortedSteps { index: 2 })
+        );
+        assert_eq!(
+            ThresholdModel::step_fn(vec![(0, 0.51), (0, 0.6)]).unwrap_err(),
+            Error::InvalidThreshold(ThresholdFault::UnsortedSteps { index: 1 })
+        );
+        assert_eq!(
+            ThresholdModel::step_fn(vec![(0, 51.0)]).unwrap_err(),
+            Error::InvalidThreshold(ThresholdFault::StepOutOfRange { index: 0, value: 51.0 })
+        );
+
+        // a dip is allowed unless the schedule has to be monotonic
+        let dip = ThresholdModel::step_fn(vec![(0, 0.7), (5, 0.6), (10, 0.8)]).unwrap();
+        assert_eq!(
+            dip.validate_monotonic(&ThresholdBounds::default()).unwrap_err(),
+            Error::InvalidThreshold(ThresholdFault::DecreasingStep { index: 1 })
+        );
+        assert_eq!(
+            ThresholdModel::Linear(-0.01).validate_monotonic(&ThresholdBounds::default()).unwrap_err(),
+            Error::InvalidThreshold(ThresholdFault::DecreasingSlope(-0.01))
+        );
+    }
+
+    #[test]
+    fn test_sub_minute_resolution() {
+        let start = Utc::now();
+        let now = start + Duration::seconds(90);
+        let model = ThresholdModel::Linear(0.02);
+        assert!((threshold_at(start, now, &model, None) - 0.54).abs() < 1e-9);
+        assert!((threshold_at_resolution(start, now, &model, None, Resolution::Minute) - 0.53).abs() < 1e-9);
+
+        let now = start + Duration::milliseconds(1500);
+        assert!((threshold_at_resolution(start, now, &model, None, Resolution::Second) - (0.51 + 0.02 / 60.0)).abs() < 1e-9);
+        assert!((threshold_at(start, now, &model, None) - (0.51 + 0.02 * 0.025)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_piecewise_linear_interpolates() {
+        let start = Utc::now();
+        let model = ThresholdModel::piecewise_linear(vec![(10, 0.61), (20, 0.81), (30, 0.85)]).unwrap();
+        let at = |secs| threshold_at(start, start + Duration::seconds(secs), &model, None);
+        assert!((at(0) - 0.51).abs() < 1e-9);
+        assert!((at(300) - 0.56).abs() < 1e-9);
+        assert!((at(600) - 0.61).abs() < 1e-9);
+        assert!((at(900) - 0.71).abs() < 1e-9);
+        assert!((at(1800) - 0.85).abs() < 1e-9);
+        assert!((at(7200) - 0.85).abs() < 1e-9);
+
+        // no jump right before a point, unlike the step function
+        let steps = ThresholdModel::StepFn(vec![(10, 0.61), (20, 0.81)]);
+        let just_before = start + Duration::seconds(1199);
+        assert!((threshold_at(start, just_before, &steps, None) - 0.61).abs() < 1e-9);
+        assert!(at(1199) > 0.80);
+
+        assert_eq!(
+            ThresholdModel::piecewise_linear(vec![(0, 0.6), (10, 0.55)]).unwrap().validate_monotonic(&ThresholdBounds::default()),
+            Err(Error::InvalidThreshold(ThresholdFault::DecreasingStep { index: 1 }))
+        );
+        // the curve starts at the floor, so a first point under it is a drop
+        let below_floor = ThresholdModel::piecewise_linear(vec![(10, 0.55), (20, 0.6)]).unwrap();
+        assert!(below_floor.validate_monotonic(&ThresholdBounds::default()).is_ok());
+        assert_eq!(
+            below_floor.validate_monotonic(&ThresholdBounds { floor: 0.58, ceiling: 0.9 }),
+            Err(Error::InvalidThreshold(ThresholdFault::DecreasingStep { index: 0 }))
+        );
+    }
+
+    #[test]
+    fn test_custom_bounds() {
+        let start = Utc::now();
+        let ms = Resolution::Millisecond;
+        let bft = ThresholdBounds::new(2.0 / 3.0, 0.95).unwrap();
+        let model = ThresholdModel::Linear(0.01);
+
+        let at_open = threshold_within(start, start, &model, None, ms, &bft);
+        assert!((at_open - 2.0 / 3.0).abs() < 1e-9);
+        let late = threshold_within(start, start + Duration::minutes(100), &model, None, ms, &bft);
+        assert!((late - 0.95).abs() < 1e-9);
+
+        // an emergency can now require 95%, but not more than the ceiling
+        let emergency = Some(ThresholdEmergency::Emergency(0.95));
+        assert!((threshold_within(start, start, &model, emergency, ms, &bft) - 0.95).abs() < 1e-9);
+        let emergency = Some(ThresholdEmergency::Emergency(0.99));
+        assert!((threshold_within(start, start, &model, emergency, ms, &bft) - 0.95).abs() < 1e-9);
+
+        // a simple majority floor
+        let low = ThresholdBounds::new(0.5, 0.9).unwrap();
+        assert_eq!(threshold_within(start, start, &ThresholdModel::StepFn(vec![]), None, ms, &low), 0.5);
+    }
+
+    #[test]
+    fn test_invalid_bounds() {
+        for (floor, ceiling) in [(0.0, 0.9), (0.8, 0.7), (0.5, 1.2), (f64::NAN, 0.9)] {
+            assert!(matches!(
+                ThresholdBounds::new(floor, ceiling),
+                Err(Error::InvalidThreshold(ThresholdFault::InvalidBounds { .. }))
+            ));
+        }
+        assert_eq!(ThresholdBounds::default(), ThresholdBounds { floor: MIN_THRESHOLD, ceiling: MAX_THRESHOLD });
+
+        // the fields are public, so unvalidated bounds must not panic
+        let start = Utc::now();
+        let model = ThresholdModel::Linear(0.01);
+        let inverted = ThresholdBounds { floor: 0.9, ceiling: 0.6 };
+        assert_eq!(threshold_within(start, start, &model, None, Resolution::Millisecond, &inverted), 0.6);
+        let emergency = Some(ThresholdEmergency::Emergency(0.75));
+        assert_eq!(threshold_within(start, start, &model, emergency, Resolution::Millisecond, &inverted), 0.6);
+        let nan = ThresholdBounds { floor: f64::NAN, ceiling: f64::NAN };
+        threshold_within(start, start, &model, None, Resolution::Millisecond, &nan);
+    }
 }
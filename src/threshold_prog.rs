@@ -39,6 +39,11 @@ impl ThresholdRequirement{
     }
 }
 pub fn threshold_at(profile:&ProgressionProfile,elapsed_time:u64,participation:f64)->f64{
+    profile_threshold(profile,elapsed_time,participation).min(0.90)
+}
+
+//threshold_at without the 90% cap, for callers that clamp to their own bounds
+pub fn profile_threshold(profile:&ProgressionProfile,elapsed_time:u64,participation:f64)->f64{
     match profile{
         ProgressionProfile::Conservative=>0.51+0.01*(elapsed_time as f64/300.0),
         ProgressionProfile::Aggresive=>0.51+0.02*(elapsed_time as f64/60.0),
@@ -50,7 +55,6 @@ pub fn threshold_at(profile:&ProgressionProfile,elapsed_time:u64,participation:f
             }
         }
     }
-    .min(0.90)
 }
 
 pub fn scheduled_base_threshold(now:u32)->f64{
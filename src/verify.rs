@@ -0,0 +1,249 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::decay::{calculate_weight_decimal, DecayModel};
+use crate::policy::ThresholdPolicy;
+
+/// Failing timestamps kept per property; `occurrences` has the full count.
+pub const MAX_COUNTEREXAMPLES: usize = 5;
+
+/// Most steps one check takes; longer windows are sampled more coarsely than
+/// `resolution` so a check stays bounded.
+pub const MAX_STEPS: i32 = 100_000;
+
+/// A property an escalation rule should hold over the whole window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    /// The threshold is a finite number at every instant.
+    Finite,
+    /// The threshold never drops as time passes.
+    Monotonic,
+    /// The threshold stays within reach of the supporting coalition, even when
+    /// it votes at that instant against opponents who voted at open.
+    Achievable,
+    /// The threshold reaches the proposal's ceiling before the window ends.
+    CeilingReached,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub property: Property,
+    /// The first failing instants, in order.
+    pub counterexamples: Vec<DateTime<Utc>>,
+    pub occurrences: usize,
+}
+
+/// Checks a proposal's whole [`ThresholdPolicy`] (base curve, profile,
+/// time-of-day schedule, proposal type and bounds) by evaluating it at every
+/// `resolution` step from `start` to `start + window`, end included. With the
+/// default one second step this covers every distinct value the decay engine
+/// can produce, up to [`MAX_STEPS`].
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    pub policy: ThresholdPolicy,
+    pub decay_model: DecayModel,
+    pub start: DateTime<Utc>,
+    pub window: Duration,
+    /// Fraction of the total weight in favour; `None` uses the policy's
+    /// ceiling, so a coalition that large must be able to pass the proposal at
+    /// any time.
+    pub support: Option<f64>,
+    /// Turnout the adaptive profile is evaluated at; defaults to everyone.
+    pub participation: f64,
+    pub resolution: Duration,
+}
+
+impl Verifier {
+    pub fn new(policy: ThresholdPolicy, decay_model: DecayModel, start: DateTime<Utc>, window: Duration) -> Self {
+        Self {
+            policy,
+            decay_model,
+            start,
+            window,
+            support: None,
+            participation: 1.0,
+            resolution: Duration::seconds(1),
+        }
+    }
+
+    fn support(&self) -> f64 {
+        self.support.unwrap_or(self.policy.bounds.ceiling)
+    }
+
+    fn step(&self) -> Duration {
+        self.resolution
+            .max(Duration::milliseconds(1))
+            .max(self.window / MAX_STEPS)
+    }
+
+    fn instants(&self) -> impl Iterator<Item = DateTime<Utc>> {
+        let end = self.start + self.window;
+        let step = self.step();
+        std::iter::successors(Some(self.start), move |t| (*t < end).then(|| (*t + step).min(end)))
+    }
+
+    fn threshold(&self, at: DateTime<Utc>) -> f64 {
+        self.policy.evaluate(self.start, at, self.participation).threshold
+    }
+
+    /// Yes share of `support` weight cast at `at` against the rest cast at open.
+    fn achievable_share(&self, at: DateTime<Utc>) -> f64 {
+        let late = calculate_weight_decimal(Decimal::ONE, self.start, at, &self.decay_model)
+            .to_f64()
+            .unwrap_or(0.0);
+        let support = self.support();
+        let yes = support * late;
+        let no = 1.0 - support;
+        if yes + no <= 0.0 { 0.0 } else { yes / (yes + no) }
+    }
+
+    pub fn check(&self, property: Property) -> Option<Violation> {
+        let mut counterexamples = Vec::new();
+        let mut occurrences = 0;
+        let mut previous: Option<f64> = None;
+        let mut reached = false;
+        for t in self.instants() {
+            let threshold = self.threshold(t);
+            let failed = match property {
+                Property::Finite => !threshold.is_finite(),
+                Property::Monotonic => previous.is_some_and(|p| threshold < p),
+                Property::Achievable => threshold.is_nan() || threshold > self.achievable_share(t),
+                Property::CeilingReached => {
+                    reached |= threshold >= self.policy.bounds.ceiling;
+                    false
+                }
+            };
+            previous = Some(threshold);
+            if failed {
+                occurrences += 1;
+                if counterexamples.len() < MAX_COUNTEREXAMPLES {
+                    counterexamples.push(t);
+                }
+            }
+        }
+        if property == Property::CeilingReached && !reached {
+            occurrences = 1;
+            counterexamples.push(self.start + self.window);
+        }
+
+        if occurrences == 0 {
+            return None;
+        }
+        Some(Violation { property, counterexamples, occurrences })
+    }
+
+    /// Every violated property; empty when the rules are sound.
+    pub fn verify(&self) -> Vec<Violation> {
+        [Property::Finite, Property::Monotonic, Property::Achievable, Property::CeilingReached]
+            .into_iter()
+            .filter_map(|p| self.check(p))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threshold::{ThresholdBounds, ThresholdModel};
+    use crate::threshold_prog::Proposaltype;
+    use chrono::TimeZone;
+
+    fn verifier(model: ThresholdModel, decay: DecayModel, minutes: i64) -> Verifier {
+        Verifier::new(ThresholdPolicy::new(model), decay, Utc::now(), Duration::minutes(minutes))
+    }
+
+    #[test]
+    fn test_sound_rules_pass() {
+        let v = verifier(ThresholdModel::Linear(0.02), DecayModel::Linear(0.0), 30);
+        assert_eq!(v.verify(), vec![]);
+    }
+
+    #[test]
+    fn test_decreasing_steps_are_caught() {
+        let v = verifier(
+            ThresholdModel::StepFn(vec![(0, 0.9), (5, 0.6), (10, 0.9)]),
+            DecayModel::Linear(0.0),
+            15,
+        );
+        let violation = v.check(Property::Monotonic).unwrap();
+        assert_eq!(violation.occurrences, 1);
+        assert_eq!(violation.counterexamples, vec![v.start + Duration::minutes(5)]);
+    }
+
+    #[test]
+    fn test_unreachable_threshold_is_caught() {
+        // late supporters decay to a 10% floor while the threshold climbs
+        let v = verifier(ThresholdModel::Linear(0.01), DecayModel::Exponential(0.001), 30);
+        let violation = v.check(Property::Achievable).unwrap();
+        let first = violation.counterexamples[0];
+        assert!(first > v.start && first < v.start + v.window);
+        assert_eq!(violation.counterexamples.len(), MAX_COUNTEREXAMPLES);
+        assert!(violation.occurrences > MAX_COUNTEREXAMPLES);
+
+        // just before the first failure the coalition could still pass it
+        let before = first - v.resolution;
+        assert!(v.threshold(before) <= v.achievable_share(before));
+    }
+
+    #[test]
+    fn test_custom_ceiling() {
+        let mut v = verifier(ThresholdModel::Linear(0.02), DecayModel::Linear(0.0), 20);
+        assert_eq!(v.check(Property::CeilingReached), None);
+        v.policy.bounds = ThresholdBounds { floor: 0.51, ceiling: 0.95 };
+        assert!(v.check(Property::CeilingReached).is_some());
+    }
+
+    #[test]
+    fn test_support_follows_the_ceiling() {
+        let mut v = verifier(ThresholdModel::Linear(0.0), DecayModel::Linear(0.0), 5);
+        v.policy.bounds = ThresholdBounds { floor: 0.6, ceiling: 0.6 };
+        assert_eq!(v.check(Property::Achievable), None);
+        v.support = Some(0.55);
+        assert!(v.check(Property::Achievable).is_some());
+    }
+
+    #[test]
+    fn test_whole_policy_is_checked() {
+        // the schedule drops from 0.70 to 0.55 at 07:00 UTC
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 6, 30, 0).unwrap();
+        let mut policy = ThresholdPolicy::new(ThresholdModel::Linear(0.0));
+        let base_only = Verifier::new(policy.clone(), DecayModel::Linear(0.0), start, Duration::hours(1));
+        assert_eq!(base_only.check(Property::Monotonic), None);
+
+        policy.time_of_day = true;
+        let scheduled = Verifier::new(policy.clone(), DecayModel::Linear(0.0), start, Duration::hours(1));
+        let violation = scheduled.check(Property::Monotonic).unwrap();
+        assert_eq!(violation.counterexamples, vec![start + Duration::minutes(30)]);
+
+        policy.time_of_day = false;
+        policy.proposal_type = Some(Proposaltype::Critical);
+        let critical = Verifier::new(policy, DecayModel::Exponential(0.001), start, Duration::hours(1));
+        assert!(critical.check(Property::Achievable).unwrap().counterexamples[0] < start + Duration::minutes(30));
+    }
+
+    #[test]
+    fn test_long_windows_are_sampled_within_the_step_cap() {
+        let mut v = verifier(ThresholdModel::Linear(0.0), DecayModel::Linear(0.0), 60 * 24 * 365);
+        v.resolution = Duration::milliseconds(1);
+        assert!(v.instants().count() <= MAX_STEPS as usize + 1);
+        assert_eq!(v.instants().last(), Some(v.start + v.window));
+        assert_eq!(v.check(Property::Finite), None);
+    }
+
+    #[test]
+    fn test_ceiling_and_nan() {
+        let slow = verifier(ThresholdModel::Linear(0.001), DecayModel::Linear(0.0), 5);
+        let violation = slow.check(Property::CeilingReached).unwrap();
+        assert_eq!(violation.counterexamples, vec![slow.start + slow.window]);
+
+        let nan = verifier(
+            ThresholdModel::Sigmoid { steepness: f64::NAN, midpoint: 1.0 },
+            DecayModel::Linear(0.0),
+            5,
+        );
+        let violation = nan.check(Property::Finite).unwrap();
+        assert_eq!(violation.counterexamples[0], nan.start);
+        assert_eq!(violation.occurrences, 301);
+    }
+}
@@ -1,67 +1,258 @@
 use chrono::{DateTime,Utc};
 use serde::{Serialize,Deserialize};
 use ed25519_dalek::{Signature,Signer,Verifier,SigningKey,VerifyingKey};
-use rand::rngs::OsRng;
 use crate::decay::{DecayModel,calculate_weight};
+use crate::encoding::{Decoder,Encoder};
+use crate::error::{Error,Result};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::BTreeMap;
 
-#[derive(Debug,Clone,Serialize,Deserialize)]
+pub const VOTE_DOMAIN:&[u8]=b"chronovote/vote/v1";
+
+//what the voter chose; part of the signed payload
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,PartialOrd,Ord,Serialize,Deserialize)]
+pub enum Choice{
+    Yes,
+    No,
+    Abstain,
+    Option(u32), //index into a multi-option ballot
+}
+
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
 pub struct Vote {
    pub voter_id: String,
    pub validator_id: String,
    pub vote_time: DateTime<Utc>,
-   pub vote_weight: f64,
-
+   pub vote_weight: Decimal,
+   pub choice: Choice,
+   pub proposal_id: String, //binds the signature to one proposal
+   pub nonce: u64,          //per-voter counter, never reused
 }
 
 impl Vote{
     
-    //to verify the vote,we conver this to bytes
-    pub fn to_bytes(&self)->Vec<u8>{
-        serde_json::to_vec(self).unwrap()
+    //canonical v1 encoding (see the encoding module); this is what gets signed
+    pub fn to_bytes(&self)->Result<Vec<u8>>{
+        let mut enc=Encoder::new(VOTE_DOMAIN);
+        enc.str(&self.proposal_id)
+            .str(&self.voter_id)
+            .str(&self.validator_id)
+            .timestamp(&self.vote_time)
+            .decimal(&self.vote_weight);
+        match self.choice{
+            Choice::Yes=>enc.u8(0),
+            Choice::No=>enc.u8(1),
+            Choice::Abstain=>enc.u8(2),
+            Choice::Option(idx)=>enc.u8(3).u32(idx),
+        };
+        enc.u64(self.nonce).finish()
     }
-    pub fn sign(&self,signing_key:&SigningKey)->SignedVote{
-        let msg=self.to_bytes();
+
+    pub fn from_bytes(bytes:&[u8])->Result<Vote>{
+        let mut dec=Decoder::new(bytes,VOTE_DOMAIN)?;
+        let proposal_id=dec.str()?;
+        let voter_id=dec.str()?;
+        let validator_id=dec.str()?;
+        let vote_time=dec.timestamp()?;
+        let vote_weight=dec.decimal()?;
+        let choice=match dec.u8()?{
+            0=>Choice::Yes,
+            1=>Choice::No,
+            2=>Choice::Abstain,
+            3=>Choice::Option(dec.u32()?),
+            tag=>return Err(Error::InvalidEncoding(format!("unknown choice tag {}",tag))),
+        };
+        let nonce=dec.u64()?;
+        dec.finish()?;
+        Ok(Vote{voter_id,validator_id,vote_time,vote_weight,choice,proposal_id,nonce})
+    }
+    //fails only if a field is too long to encode
+    pub fn sign(&self,signing_key:&SigningKey)->Result<SignedVote>{
+        let msg=self.to_bytes()?;
         let signature=signing_key.sign(&msg);
-        SignedVote{
+        Ok(SignedVote{
             vote:self.clone(),
             signature,
             public_key:signing_key.verifying_key(),
-        }
+        })
     }
 }
 
-#[derive(Debug,Clone)]
+//JSON form carries the signature and key as base64; encode/decode give the compact binary form
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
 pub struct SignedVote{
    pub vote:Vote,
+   #[serde(with="base64_signature")]
    pub signature:Signature,
+   #[serde(with="base64_key")]
    pub public_key:VerifyingKey,
 }
 
+pub(crate) mod base64_signature{
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use ed25519_dalek::Signature;
+    use serde::{Deserialize,Deserializer,Serializer,de::Error};
+
+    pub fn serialize<S:Serializer>(sig:&Signature,s:S)->Result<S::Ok,S::Error>{
+        s.serialize_str(&BASE64.encode(sig.to_bytes()))
+    }
+
+    pub fn deserialize<'de,D:Deserializer<'de>>(d:D)->Result<Signature,D::Error>{
+        let bytes=BASE64.decode(String::deserialize(d)?).map_err(D::Error::custom)?;
+        let bytes:[u8;64]=bytes.try_into().map_err(|_| D::Error::custom("signature must be 64 bytes"))?;
+        Ok(Signature::from_bytes(&bytes))
+    }
+}
+
+pub(crate) mod base64_key{
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use ed25519_dalek::VerifyingKey;
+    use serde::{Deserialize,Deserializer,Serializer,de::Error};
+
+    pub fn serialize<S:Serializer>(key:&VerifyingKey,s:S)->Result<S::Ok,S::Error>{
+        s.serialize_str(&BASE64.encode(key.as_bytes()))
+    }
+
+    pub fn deserialize<'de,D:Deserializer<'de>>(d:D)->Result<VerifyingKey,D::Error>{
+        let bytes=BASE64.decode(String::deserialize(d)?).map_err(D::Error::custom)?;
+        let bytes:[u8;32]=bytes.try_into().map_err(|_| D::Error::custom("public key must be 32 bytes"))?;
+        VerifyingKey::from_bytes(&bytes).map_err(D::Error::custom)
+    }
+}
+
 impl SignedVote{
     //to verify the signed vote, we need to check if the signature is valid
     //using the public key of the validator
     pub fn verify(&self)->bool{
-        let msg=self.vote.to_bytes();
-        self.public_key
-            .verify(&msg,&self.signature)
-            .is_ok()
+        self.vote
+            .to_bytes()
+            .is_ok_and(|msg| self.public_key.verify(&msg,&self.signature).is_ok())
+    }
+
+    //vote bytes (u32 BE length prefixed) | signature (64) | public key (32)
+    pub fn encode(&self)->Result<Vec<u8>>{
+        let msg=self.vote.to_bytes()?;
+        let len=u32::try_from(msg.len())
+            .map_err(|_| Error::InvalidEncoding(format!("signed vote: {} byte vote",msg.len())))?;
+        let mut out=Vec::with_capacity(4+msg.len()+64+32);
+        out.extend_from_slice(&len.to_be_bytes());
+        out.extend_from_slice(&msg);
+        out.extend_from_slice(&self.signature.to_bytes());
+        out.extend_from_slice(self.public_key.as_bytes());
+        Ok(out)
+    }
+
+    //inverse of encode
+    pub fn decode(bytes:&[u8])->Result<SignedVote>{
+        let invalid=|what:&str| Error::InvalidEncoding(format!("signed vote: {}",what));
+        let len=bytes
+            .get(..4)
+            .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+            .ok_or_else(|| invalid("truncated length"))?;
+        if bytes.len()!=4+len+64+32{
+            return Err(invalid("wrong length"));
+        }
+        let vote=Vote::from_bytes(&bytes[4..4+len])?;
+        let signature=Signature::from_bytes(bytes[4+len..4+len+64].try_into().unwrap());
+        let public_key=VerifyingKey::from_bytes(bytes[4+len+64..].try_into().unwrap())
+            .map_err(|_| invalid("bad public key"))?;
+        Ok(SignedVote{vote,signature,public_key})
+    }
+
+    pub fn ensure_valid(&self)->Result<()>{
+        if self.verify(){
+            Ok(())
+        }else{
+            Err(Error::InvalidSignature{voter_id:self.vote.voter_id.clone()})
+        }
     }
 
     pub fn compute_weight(&self,current_time:DateTime<Utc>,decay_model:DecayModel)->f64{
         calculate_weight(
-            self.vote.vote_weight,
+            self.vote.vote_weight.to_f64().unwrap_or(0.0),
             self.vote.vote_time, // This is the vote's timestamp
             current_time,       // This is the current time
             decay_model,
        )
     }
 }
+//head count of every choice in a set of votes
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct ChoiceCounts{
+    pub yes:usize,
+    pub no:usize,
+    pub abstain:usize,
+    pub options:BTreeMap<u32,usize>,
+}
+
+impl ChoiceCounts{
+    pub fn add(&mut self,choice:Choice){
+        match choice{
+            Choice::Yes=>self.yes+=1,
+            Choice::No=>self.no+=1,
+            Choice::Abstain=>self.abstain+=1,
+            Choice::Option(idx)=>*self.options.entry(idx).or_insert(0)+=1,
+        }
+    }
+
+    //votes that take a side; abstentions are not counted
+    pub fn decisive(&self)->usize{
+        self.yes+self.no+self.options.values().sum::<usize>()
+    }
+
+    pub fn total(&self)->usize{
+        self.decisive()+self.abstain
+    }
+}
+
+pub fn count_choices(votes:&[SignedVote])->ChoiceCounts{
+    let mut counts=ChoiceCounts::default();
+    for signed in votes{
+        counts.add(signed.vote.choice);
+    }
+    counts
+}
+
+//checks all signatures in one ed25519 batch; if the batch fails, every vote is
+//checked on its own so the error can name the offending indices
+pub fn verify_batch(votes:&[SignedVote])->Result<()>{
+    if votes.is_empty(){
+        return Ok(());
+    }
+    //a vote that can't be encoded can't verify either; the per-vote pass names it
+    let messages:Result<Vec<Vec<u8>>>=votes.iter().map(|v| v.vote.to_bytes()).collect();
+    if let Ok(messages)=messages{
+        let message_refs:Vec<&[u8]>=messages.iter().map(|m| m.as_slice()).collect();
+        let signatures:Vec<Signature>=votes.iter().map(|v| v.signature).collect();
+        let keys:Vec<VerifyingKey>=votes.iter().map(|v| v.public_key).collect();
+        if ed25519_dalek::verify_batch(&message_refs,&signatures,&keys).is_ok(){
+            return Ok(());
+        }
+    }
+
+    let invalid:Vec<usize>=votes
+        .iter()
+        .enumerate()
+        .filter(|(_,v)| !v.verify())
+        .map(|(i,_)| i)
+        .collect();
+    if invalid.is_empty(){
+        Ok(())
+    }else{
+        Err(Error::InvalidSignatures(invalid))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ed25519_dalek::{SigningKey,VerifyingKey};
+    use ed25519_dalek::SigningKey;
     use chrono::Utc;
     use rand::rngs::OsRng;
+    use rust_decimal_macros::dec;
 
     #[test]
     fn test_vote_serialization() {
@@ -69,10 +260,13 @@ mod tests {
             voter_id: "Alice".into(),
             validator_id: "Validator1".into(),
             vote_time: Utc::now(),
-            vote_weight: 1.0,
+            vote_weight: dec!(1.0),
+            choice: Choice::Yes,
+            proposal_id: "p1".into(),
+            nonce: 0,
         };
 
-        let bytes = vote.to_bytes();
+        let bytes = vote.to_bytes().unwrap();
         assert!(!bytes.is_empty(), "Vote did not serialize correctly");
     }
 
@@ -84,11 +278,14 @@ mod tests {
             voter_id: "Bob".into(),
             validator_id: "Validator2".into(),
             vote_time: Utc::now(),
-            vote_weight: 1.5,
+            vote_weight: dec!(1.5),
+            choice: Choice::No,
+            proposal_id: "p1".into(),
+            nonce: 0,
         };
 
-        let signed_vote = vote.sign(&signing_key);
-        let msg = vote.to_bytes();
+        let signed_vote = vote.sign(&signing_key).unwrap();
+        let msg = vote.to_bytes().unwrap();
 
         // Use the public key to verify the signature
         assert!(
@@ -96,4 +293,213 @@ mod tests {
             "Signature verification failed"
         );
     }
+
+    #[test]
+    fn test_ensure_valid_rejects_tampered_vote() {
+        let mut csprng = OsRng;
+        let signing_key = SigningKey::generate(&mut csprng);
+        let vote = Vote {
+            voter_id: "Carol".into(),
+            validator_id: "Validator3".into(),
+            vote_time: Utc::now(),
+            vote_weight: dec!(1.0),
+            choice: Choice::Yes,
+            proposal_id: "p1".into(),
+            nonce: 0,
+        };
+
+        let mut signed_vote = vote.sign(&signing_key).unwrap();
+        assert!(signed_vote.ensure_valid().is_ok());
+
+        signed_vote.vote.vote_weight = dec!(10.0);
+        assert_eq!(
+            signed_vote.ensure_valid(),
+            Err(Error::InvalidSignature { voter_id: "Carol".into() })
+        );
+    }
+
+    #[test]
+    fn test_choice_is_covered_by_signature() {
+        let mut csprng = OsRng;
+        let signing_key = SigningKey::generate(&mut csprng);
+        let vote = Vote {
+            voter_id: "Dave".into(),
+            validator_id: "Validator4".into(),
+            vote_time: Utc::now(),
+            vote_weight: dec!(1.0),
+            choice: Choice::No,
+            proposal_id: "p1".into(),
+            nonce: 0,
+        };
+
+        let mut signed_vote = vote.sign(&signing_key).unwrap();
+        signed_vote.vote.choice = Choice::Yes;
+        assert!(!signed_vote.verify());
+    }
+
+    #[test]
+    fn test_count_choices() {
+        let mut csprng = OsRng;
+        let signing_key = SigningKey::generate(&mut csprng);
+        let choices = [
+            Choice::Yes,
+            Choice::Yes,
+            Choice::No,
+            Choice::Abstain,
+            Choice::Option(2),
+        ];
+        let votes: Vec<SignedVote> = choices
+            .iter()
+            .enumerate()
+            .map(|(i, choice)| {
+                Vote {
+                    voter_id: format!("voter{}", i),
+                    validator_id: "Validator1".into(),
+                    vote_time: Utc::now(),
+                    vote_weight: dec!(1.0),
+                    choice: *choice,
+                    proposal_id: "p1".into(),
+                    nonce: 0,
+                }
+                .sign(&signing_key)
+                .unwrap()
+            })
+            .collect();
+
+        let counts = count_choices(&votes);
+        assert_eq!(counts.yes, 2);
+        assert_eq!(counts.no, 1);
+        assert_eq!(counts.abstain, 1);
+        assert_eq!(counts.options.get(&2), Some(&1));
+        assert_eq!(counts.decisive(), 4);
+        assert_eq!(counts.total(), 5);
+    }
+
+    // Test vector for third-party implementations of the v1 vote encoding.
+    // Signing key: 32 bytes of 0x07.
+    #[test]
+    fn test_canonical_vote_vector() {
+        use chrono::TimeZone;
+
+        let vote = Vote {
+            voter_id: "Alice".into(),
+            validator_id: "Val1".into(),
+            vote_time: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+            vote_weight: dec!(1.50),
+            choice: Choice::Option(2),
+            proposal_id: "p1".into(),
+            nonce: 42,
+        };
+        let expected = concat!(
+            "000000126368726f6e6f766f74652f766f74652f7631", // "chronovote/vote/v1"
+            "000000027031",                         // proposal_id "p1"
+            "00000005416c696365",                   // voter_id "Alice"
+            "0000000456616c31",                     // validator_id "Val1"
+            "000000006553f100", "00000000",         // 2023-11-14T22:13:20Z
+            "0000000000000000000000000000000f", "00000001", // 1.5
+            "03", "00000002",                       // Option(2)
+            "000000000000002a",                     // nonce 42
+        );
+        assert_eq!(hex(&vote.to_bytes().unwrap()), expected);
+        assert_eq!(Vote::from_bytes(&vote.to_bytes().unwrap()).unwrap(), vote);
+
+        let signed = vote.sign(&SigningKey::from_bytes(&[7u8; 32])).unwrap();
+        assert_eq!(
+            hex(signed.public_key.as_bytes()),
+            "ea4a6c63e29c520abef5507b132ec5f9954776aebebe7b92421eea691446d22c"
+        );
+        assert_eq!(
+            hex(&signed.signature.to_bytes()),
+            concat!(
+                "e4677a9b6fa9fb31d774e9da0ae4dbdc544d3c798768006842792bd8eb82e5dd",
+                "14326fced81b659b0ff49a49518defd2dbf40131f930b364dff903fdb318e706",
+            )
+        );
+        assert!(SignedVote::decode(&signed.encode().unwrap()).unwrap().verify());
+    }
+
+    #[test]
+    fn test_vote_decoding_rejects_garbage() {
+        assert!(Vote::from_bytes(b"chronovote/vote/v2").is_err());
+        assert!(Vote::from_bytes(b"chronovote/vote/v1\x00").is_err());
+        assert!(SignedVote::decode(&[0, 0, 0, 1]).is_err());
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_signed_vote_json_round_trip() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let signed = Vote {
+            voter_id: "Erin".into(),
+            validator_id: "Validator5".into(),
+            vote_time: Utc::now(),
+            vote_weight: dec!(2.25),
+            choice: Choice::Abstain,
+            proposal_id: "p1".into(),
+            nonce: 3,
+        }
+        .sign(&signing_key)
+        .unwrap();
+
+        let json = serde_json::to_value(&signed).unwrap();
+        assert!(json["signature"].is_string());
+        assert!(json["public_key"].is_string());
+
+        let restored: SignedVote = serde_json::from_value(json).unwrap();
+        assert!(restored.verify());
+        assert_eq!(restored.vote, signed.vote);
+        assert_eq!(restored.signature, signed.signature);
+
+        let compact = SignedVote::decode(&signed.encode().unwrap()).unwrap();
+        assert!(compact.verify());
+        assert_eq!(compact.public_key, signed.public_key);
+    }
+
+    #[test]
+    fn test_signed_vote_json_rejects_bad_base64() {
+        let signed = Vote {
+            voter_id: "Erin".into(),
+            validator_id: "Validator5".into(),
+            vote_time: Utc::now(),
+            vote_weight: dec!(1),
+            choice: Choice::Yes,
+            proposal_id: "p1".into(),
+            nonce: 4,
+        }
+        .sign(&SigningKey::generate(&mut OsRng))
+        .unwrap();
+
+        let mut json = serde_json::to_value(&signed).unwrap();
+        json["signature"] = "c2hvcnQ=".into();
+        assert!(serde_json::from_value::<SignedVote>(json).is_err());
+    }
+
+    #[test]
+    fn test_verify_batch_pinpoints_invalid_votes() {
+        let votes: Vec<SignedVote> = (0..20)
+            .map(|i| {
+                Vote {
+                    voter_id: format!("voter{}", i),
+                    validator_id: "Validator1".into(),
+                    vote_time: Utc::now(),
+                    vote_weight: dec!(1),
+                    choice: Choice::Yes,
+                    proposal_id: "p1".into(),
+                    nonce: i,
+                }
+                .sign(&SigningKey::generate(&mut OsRng))
+                .unwrap()
+            })
+            .collect();
+        assert!(verify_batch(&votes).is_ok());
+        assert!(verify_batch(&[]).is_ok());
+
+        let mut tampered = votes.clone();
+        tampered[3].vote.choice = Choice::No;
+        tampered[17].signature = tampered[16].signature;
+        assert_eq!(verify_batch(&tampered), Err(Error::InvalidSignatures(vec![3, 17])));
+    }
 }
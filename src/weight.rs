@@ -1,9 +1,14 @@
-use crate::{decay::{calculate_weight, DecayModel}, voter};
-use chrono::{DateTime, Utc};
+use crate::attestation::{AttestedVote, DriftMonitor, QuorumAttestedVote, QuorumPolicy};
+use crate::clock::{system_clock, Clock};
+use crate::decay::{calculate_weight_decimal, DecayModel};
+use crate::error::{Error, Result};
+use crate::registry::{Registry, Role};
+use crate::voter::{verify_batch, SignedVote};
+use chrono::{DateTime, Duration, Utc};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use std::collections::HashMap;
-use rust_decimal::prelude::ToPrimitive;
+use std::sync::Arc;
 
 
 #[derive(Debug, Clone)]
@@ -14,24 +19,53 @@ pub struct WeightedVote {
     pub decay_model: DecayModel,
     pub reputation_bonus: Decimal,
 }
+/// Which interval a vote's weight decays over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecayBasis {
+    /// From proposal open until the vote was cast, then frozen: early votes
+    /// weigh more and a cast vote's weight never changes.
+    #[default]
+    CastTime,
+    /// From proposal open until `now`, so every vote keeps losing weight
+    /// for as long as the proposal runs.
+    Ongoing,
+}
+
 impl WeightedVote {
+    /// Weight under [`DecayBasis::CastTime`]; `now` does not affect the result.
     pub fn effective_weight(&self, vote_start: DateTime<Utc>, now: DateTime<Utc>) -> Decimal {
-        let decayed = Decimal::from_f64_retain(calculate_weight(
-            self.orig_weight.to_f64().unwrap_or(0.0),
-            vote_start,
-            now,
-            self.decay_model.clone(),
-        ))
-        .unwrap_or(dec!(0.0));
+        self.effective_weight_with(DecayBasis::CastTime, vote_start, now)
+    }
+
+    pub fn effective_weight_with(&self, basis: DecayBasis, vote_start: DateTime<Utc>, now: DateTime<Utc>) -> Decimal {
+        // a vote stamped before the proposal opened gets no extra weight for it
+        let decay_until = match basis {
+            DecayBasis::CastTime => self.vote_time,
+            DecayBasis::Ongoing => now,
+        }
+        .max(vote_start);
+        let decayed = calculate_weight_decimal(self.orig_weight, vote_start, decay_until, &self.decay_model);
         decayed * (dec!(1.0) + self.reputation_bonus)
     }
 }
 
-#[derive(Default)]
 pub struct WeightEngine {
     pub cache: HashMap<String, Decimal>,
     pub history: Vec<(String, Decimal, DateTime<Utc>)>,
     pub reputation: HashMap<String, Decimal>,
+    pub clock: Arc<dyn Clock>,
+    // how far a vote's claimed time may be from its attested time
+    pub drift_tolerance: Duration,
+    pub quorum: QuorumPolicy,
+    pub decay_basis: DecayBasis,
+    // receipt offsets seen by calculate_quorum, per validator
+    pub clock_drift: DriftMonitor,
+}
+
+impl Default for WeightEngine {
+    fn default() -> Self {
+        Self::with_clock(system_clock())
+    }
 }
 
 impl WeightEngine {
@@ -39,13 +73,32 @@ impl WeightEngine {
         Self::default()
     }
 
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            cache: HashMap::new(),
+            history: Vec::new(),
+            reputation: HashMap::new(),
+            clock,
+            drift_tolerance: Duration::seconds(30),
+            quorum: QuorumPolicy::default(),
+            decay_basis: DecayBasis::default(),
+            clock_drift: DriftMonitor::new(),
+        }
+    }
+
+    // same as calculate_and_cache, evaluated at the engine's clock
+    pub fn calculate_now(&mut self, vote: &WeightedVote, vote_start: &DateTime<Utc>) -> Decimal {
+        let now = self.clock.now();
+        self.calculate_and_cache(vote, vote_start, now)
+    }
+
     pub fn calculate_and_cache(
         &mut self,
         vote: &WeightedVote,
         vote_start: &DateTime<Utc>,
         now: DateTime<Utc>,
     ) -> Decimal {
-        let weight = vote.effective_weight(*vote_start, now);
+        let weight = vote.effective_weight_with(self.decay_basis, *vote_start, now);
         self.cache.insert(vote.voter_id.clone(), weight);
         self.history.push((vote.voter_id.clone(), weight, now));
         weight
@@ -64,6 +117,109 @@ impl WeightEngine {
         }
         results
     }
+    // Verifies a batch of signed votes in one go and feeds the valid ones through
+    // batch_updates. A vote is rejected when its signature fails or its key isn't
+    // the one currently registered for its voter; the claimed vote time is the
+    // voter's own and never selects an older key. Returns the new weights and
+    // the indices of rejected votes; an invalid decay model fails the whole batch.
+    pub fn ingest_signed(
+        &mut self,
+        registry: &Registry,
+        votes: &[SignedVote],
+        decay_model: &DecayModel,
+        vote_start: &DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<(HashMap<String, Decimal>, Vec<usize>)> {
+        decay_model.validate()?;
+        let mut rejected = vec![false; votes.len()];
+        match verify_batch(votes) {
+            Ok(()) => {}
+            Err(Error::InvalidSignatures(indices)) => indices.into_iter().for_each(|i| rejected[i] = true),
+            Err(err) => return Err(err),
+        }
+        for (i, signed) in votes.iter().enumerate() {
+            let registered = registry.key_for(Role::Voter, &signed.vote.voter_id);
+            if registered != Some(&signed.public_key) {
+                rejected[i] = true;
+            }
+        }
+
+        let weighted: Vec<WeightedVote> = votes
+            .iter()
+            .zip(&rejected)
+            .filter(|(_, rejected)| !**rejected)
+            .map(|(signed, _)| WeightedVote {
+                voter_id: signed.vote.voter_id.clone(),
+                vote_time: signed.vote.vote_time,
+                orig_weight: signed.vote.vote_weight,
+                decay_model: decay_model.clone(),
+                reputation_bonus: self.reputation.get(&signed.vote.voter_id).cloned().unwrap_or(dec!(0.0)),
+            })
+            .collect();
+        let rejected = (0..votes.len()).filter(|i| rejected[*i]).collect();
+        Ok((self.batch_updates(&weighted, vote_start, now), rejected))
+    }
+
+    // Weighs a vote at the time its validator attested to rather than the time the
+    // voter claims. Fails unless both signatures come from the keys registered for
+    // the voter and validator (see Registry::verify_attested), on drift beyond
+    // drift_tolerance, or when the decay model does not validate.
+    pub fn calculate_attested(
+        &mut self,
+        registry: &Registry,
+        vote: &AttestedVote,
+        decay_model: &DecayModel,
+        vote_start: &DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Decimal> {
+        decay_model.validate()?;
+        registry.verify_attested(vote)?;
+        vote.check_drift(self.drift_tolerance)?;
+        Ok(self.calculate_at(&vote.vote, vote.attested_time(), decay_model, vote_start, now))
+    }
+
+    // Like calculate_attested, but the vote time is the median of a quorum of
+    // receipts from registered validators (see Registry::verify_quorum). Every
+    // quorum that passes the drift check is fed to clock_drift.
+    pub fn calculate_quorum(
+        &mut self,
+        registry: &Registry,
+        vote: &QuorumAttestedVote,
+        decay_model: &DecayModel,
+        vote_start: &DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Decimal> {
+        decay_model.validate()?;
+        let quorum = registry.verify_quorum(vote, &self.quorum)?;
+        if (quorum.time - vote.vote.vote.vote_time).abs() > self.drift_tolerance {
+            return Err(Error::TimestampDrift {
+                voter_id: vote.vote.vote.voter_id.clone(),
+                claimed: vote.vote.vote.vote_time,
+                attested: quorum.time,
+            });
+        }
+        self.clock_drift.record(&quorum);
+        Ok(self.calculate_at(&vote.vote, quorum.time, decay_model, vote_start, now))
+    }
+
+    fn calculate_at(
+        &mut self,
+        signed: &SignedVote,
+        vote_time: DateTime<Utc>,
+        decay_model: &DecayModel,
+        vote_start: &DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Decimal {
+        let weighted = WeightedVote {
+            voter_id: signed.vote.voter_id.clone(),
+            vote_time,
+            orig_weight: signed.vote.vote_weight,
+            decay_model: decay_model.clone(),
+            reputation_bonus: self.reputation.get(&signed.vote.voter_id).cloned().unwrap_or(dec!(0.0)),
+        };
+        self.calculate_and_cache(&weighted, vote_start, now)
+    }
+
     pub fn set_reputation(&mut self,voter_id:&String,bonus:Decimal){
         self.reputation.insert(voter_id.to_string(),bonus);
     }
@@ -109,6 +265,30 @@ mod tests {
         assert!(weight <= dec!(1.1));
     }
 
+    #[test]
+    fn test_early_votes_weigh_more_and_stay_frozen() {
+        let vote_start = Utc::now();
+        let model = DecayModel::Linear(0.001);
+        let mut early = sample_vote("alice", dec!(1.0), dec!(0.0), model.clone());
+        early.vote_time = vote_start + Duration::seconds(100);
+        let mut late = sample_vote("bob", dec!(1.0), dec!(0.0), model);
+        late.vote_time = vote_start + Duration::seconds(500);
+
+        let soon = vote_start + Duration::seconds(600);
+        let much_later = vote_start + Duration::minutes(60);
+        assert_eq!(early.effective_weight(vote_start, soon), dec!(0.9));
+        assert_eq!(late.effective_weight(vote_start, soon), dec!(0.5));
+        assert_eq!(early.effective_weight(vote_start, much_later), dec!(0.9));
+
+        // with ongoing decay both are on the floor by then
+        assert_eq!(early.effective_weight_with(DecayBasis::Ongoing, vote_start, much_later), dec!(0.1));
+        assert_eq!(late.effective_weight_with(DecayBasis::Ongoing, vote_start, much_later), dec!(0.1));
+
+        // a backdated vote is treated as cast at open
+        early.vote_time = vote_start - Duration::seconds(100);
+        assert_eq!(early.effective_weight(vote_start, soon), dec!(1.0));
+    }
+
     #[test]
     fn test_cache_and_history() {
         let vote_start = Utc::now();
@@ -161,4 +341,188 @@ mod tests {
         let bonus = engine.reputation.get("carol").unwrap();
         assert_eq!(*bonus, dec!(0.3));
     }
+
+    #[test]
+    fn test_calculate_now_uses_engine_clock() {
+        use crate::clock::SimulatedClock;
+
+        let vote_start = Utc::now();
+        let clock = Arc::new(SimulatedClock::new(vote_start));
+        let mut engine = WeightEngine::with_clock(clock.clone());
+        engine.decay_basis = DecayBasis::Ongoing;
+        let vote = sample_vote("dave", dec!(1.0), dec!(0.0), DecayModel::Linear(0.001));
+
+        assert_eq!(engine.calculate_now(&vote, &vote_start), dec!(1.0));
+
+        clock.advance(Duration::seconds(100));
+        let later = engine.calculate_now(&vote, &vote_start);
+        assert!(later < dec!(1.0));
+        assert_eq!(engine.get_history()[1].2, vote_start + Duration::seconds(100));
+    }
+
+    #[test]
+    fn test_ingest_signed_skips_invalid_votes() {
+        use crate::voter::{Choice, Vote};
+        use ed25519_dalek::SigningKey;
+        use rand::rngs::OsRng;
+
+        let vote_start = Utc::now();
+        let mut registry = Registry::new();
+        let mut votes: Vec<SignedVote> = ["alice", "bob", "carol", "dave"]
+            .iter()
+            .map(|voter| {
+                let key = SigningKey::generate(&mut OsRng);
+                if *voter != "dave" {
+                    registry.register(Role::Voter, voter, key.verifying_key(), vote_start).unwrap();
+                }
+                Vote {
+                    voter_id: voter.to_string(),
+                    validator_id: "val".to_string(),
+                    vote_time: vote_start,
+                    vote_weight: dec!(2.0),
+                    choice: Choice::Yes,
+                    proposal_id: "p1".to_string(),
+                    nonce: 1,
+                }
+                .sign(&key)
+                .unwrap()
+            })
+            .collect();
+        votes[1].vote.vote_weight = dec!(50.0);
+
+        let mut engine = WeightEngine::new();
+        engine.set_reputation(&"carol".to_string(), dec!(0.5));
+        let model = DecayModel::Linear(0.0);
+        let (weights, rejected) = engine.ingest_signed(&registry, &votes, &model, &vote_start, vote_start).unwrap();
+
+        // bob's signature no longer matches; dave's key was never registered
+        assert_eq!(rejected, vec![1, 3]);
+        assert_eq!(weights.len(), 2);
+        assert_eq!(weights["alice"], dec!(2.0));
+        assert_eq!(weights["carol"], dec!(3.0));
+        assert!(engine.get_cached_weight("bob").is_none());
+        assert!(engine.get_cached_weight("dave").is_none());
+
+        // a valid signature under a key other than the registered one
+        let impostor = votes[0].vote.sign(&SigningKey::generate(&mut OsRng)).unwrap();
+        let (weights, rejected) = engine.ingest_signed(&registry, &[impostor], &model, &vote_start, vote_start).unwrap();
+        assert!(weights.is_empty());
+        assert_eq!(rejected, vec![0]);
+    }
+
+    #[test]
+    fn test_calculate_attested_checks_drift() {
+        use crate::voter::{Choice, Vote};
+        use ed25519_dalek::SigningKey;
+        use rand::rngs::OsRng;
+
+        let vote_start = Utc::now();
+        let voter = SigningKey::generate(&mut OsRng);
+        let validator = SigningKey::generate(&mut OsRng);
+        let mut registry = Registry::new();
+        let registered_at = vote_start - Duration::hours(1);
+        registry.register(Role::Voter, "alice", voter.verifying_key(), registered_at).unwrap();
+        registry.register(Role::Validator, "val", validator.verifying_key(), registered_at).unwrap();
+        let attested_at = |claimed: DateTime<Utc>, validator: &SigningKey| {
+            let signed = Vote {
+                voter_id: "alice".to_string(),
+                validator_id: "val".to_string(),
+                vote_time: claimed,
+                vote_weight: dec!(1.0),
+                choice: Choice::Yes,
+                proposal_id: "p1".to_string(),
+                nonce: 1,
+            }
+            .sign(&voter)
+            .unwrap();
+            AttestedVote::attest(signed, validator, vote_start + Duration::seconds(60)).unwrap()
+        };
+
+        let mut engine = WeightEngine::new();
+        let model = DecayModel::Linear(0.0);
+        let on_time = attested_at(vote_start + Duration::seconds(50), &validator);
+        assert_eq!(engine.calculate_attested(&registry, &on_time, &model, &vote_start, vote_start), Ok(dec!(1.0)));
+        assert!(matches!(
+            engine.calculate_attested(&registry, &on_time, &DecayModel::Exponential(-1.0), &vote_start, vote_start),
+            Err(Error::InvalidDecay(_))
+        ));
+
+        // a receipt the voter made up under the validator's name
+        let self_attested = attested_at(vote_start + Duration::seconds(50), &voter);
+        assert_eq!(
+            engine.calculate_attested(&registry, &self_attested, &model, &vote_start, vote_start),
+            Err(Error::KeyMismatch { role: Role::Validator, id: "val".to_string() })
+        );
+
+        // backdated by five minutes to claim a heavier weight
+        let backdated = attested_at(vote_start - Duration::seconds(240), &validator);
+        assert!(matches!(
+            engine.calculate_attested(&registry, &backdated, &model, &vote_start, vote_start),
+            Err(Error::TimestampDrift { .. })
+        ));
+
+        engine.drift_tolerance = Duration::minutes(10);
+        assert!(engine.calculate_attested(&registry, &backdated, &model, &vote_start, vote_start).is_ok());
+    }
+
+    #[test]
+    fn test_calculate_quorum_records_drift() {
+        use crate::attestation::Attestation;
+        use crate::voter::{Choice, Vote};
+        use ed25519_dalek::SigningKey;
+        use rand::rngs::OsRng;
+
+        let vote_start = Utc::now();
+        let voter = SigningKey::generate(&mut OsRng);
+        let mut registry = Registry::new();
+        let registered_at = vote_start - Duration::hours(1);
+        registry.register(Role::Voter, "alice", voter.verifying_key(), registered_at).unwrap();
+        let signed = |vote_time, nonce| {
+            Vote {
+                voter_id: "alice".to_string(),
+                validator_id: "Val1".to_string(),
+                vote_time,
+                vote_weight: dec!(1.0),
+                choice: Choice::Yes,
+                proposal_id: "p1".to_string(),
+                nonce,
+            }
+            .sign(&voter)
+            .unwrap()
+        };
+        let mut vote = QuorumAttestedVote::new(signed(vote_start, 1));
+
+        let mut engine = WeightEngine::new();
+        let model = DecayModel::Linear(0.0);
+        let mut validators = Vec::new();
+        for (id, offset) in [("Val1", 0), ("Val2", 1), ("Val3", -300)] {
+            let key = SigningKey::generate(&mut OsRng);
+            registry.register(Role::Validator, id, key.verifying_key(), registered_at).unwrap();
+            let receipt = Attestation::sign(&vote.vote, id, &key, vote_start + Duration::seconds(offset));
+            vote.add(receipt.unwrap());
+            validators.push((id, key));
+        }
+        // Val3 is an outlier, leaving only two of the three required receipts
+        assert!(matches!(
+            engine.calculate_quorum(&registry, &vote, &model, &vote_start, vote_start),
+            Err(Error::NoTimestampQuorum { attestations: 2, .. })
+        ));
+
+        engine.quorum.min_attestations = 2;
+        assert_eq!(engine.calculate_quorum(&registry, &vote, &model, &vote_start, vote_start), Ok(dec!(1.0)));
+        assert_eq!(engine.clock_drift.stats["Val3"].outliers, 1);
+        assert_eq!(engine.clock_drift.stats["Val1"].samples, 1);
+
+        // backdated past the tolerance: rejected, and the receipts are not counted
+        let mut backdated = QuorumAttestedVote::new(signed(vote_start - Duration::minutes(5), 2));
+        for (id, key) in &validators[..2] {
+            backdated.add(Attestation::sign(&backdated.vote, id, key, vote_start).unwrap());
+        }
+        assert!(matches!(
+            engine.calculate_quorum(&registry, &backdated, &model, &vote_start, vote_start),
+            Err(Error::TimestampDrift { .. })
+        ));
+        assert_eq!(engine.clock_drift.stats["Val1"].samples, 1);
+        assert_eq!(engine.clock_drift.stats["Val2"].samples, 1);
+    }
 }
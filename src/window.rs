@@ -1,5 +1,16 @@
 use chrono::{DateTime, Duration, Utc};
+use serde::{Serialize,Deserialize};
 use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::clock::{system_clock,Clock};
+use crate::decay::DecayModel;
+use crate::error::{Error,Result};
+use crate::policy::ThresholdPolicy;
+use crate::tally::{Tally,TallyOutcome,TallyStatus};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
 #[derive(Debug,Clone)]
 pub enum VotingWindow{
     Short, //5 min
@@ -19,15 +30,90 @@ impl VotingWindow{
     }
 }
 
+// Where a proposal is in its life. Votes are only accepted while Open or Extended.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+pub enum ProposalState{
+    Draft,
+    Open,
+    Extended,
+    Closed,
+    Passed,
+    Rejected,
+    Expired,
+    Executed,
+    Cancelled,
+}
+
+impl ProposalState{
+    pub fn can_transition_to(&self,next:ProposalState)->bool{
+        use ProposalState::*;
+        matches!(
+            (self,next),
+            (Draft,Open)
+                | (Draft,Cancelled)
+                | (Open,Extended)
+                | (Open,Closed)
+                | (Open,Cancelled)
+                | (Extended,Extended)
+                | (Extended,Closed)
+                | (Extended,Cancelled)
+                | (Closed,Passed)
+                | (Closed,Rejected)
+                | (Closed,Expired)
+                | (Passed,Executed)
+                | (Passed,Cancelled)
+        )
+    }
+
+    pub fn is_voting(&self)->bool{
+        matches!(self,ProposalState::Open|ProposalState::Extended)
+    }
+
+    pub fn is_terminal(&self)->bool{
+        matches!(
+            self,
+            ProposalState::Rejected|ProposalState::Expired|ProposalState::Executed|ProposalState::Cancelled
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct VotingSession{
     pub vote_start:DateTime<Utc>,
     pub voter_id:String,
     pub voting_window:VotingWindow,
     pub extended:bool,
+    pub extensions:u32,
+    pub state:ProposalState,
+    pub transitions:Vec<(ProposalState,DateTime<Utc>)>, //every state entered, oldest first
 }
 
 impl VotingSession{
+    //a session that opened for voting at vote_start
+    pub fn new(vote_start:DateTime<Utc>,voter_id:String,voting_window:VotingWindow)->Self{
+        Self{
+            vote_start,
+            voter_id,
+            voting_window,
+            extended:false,
+            extensions:0,
+            state:ProposalState::Open,
+            transitions:vec![(ProposalState::Open,vote_start)],
+        }
+    }
+
+    pub fn draft(created_at:DateTime<Utc>,voter_id:String,voting_window:VotingWindow)->Self{
+        Self{
+            vote_start:created_at,
+            voter_id,
+            voting_window,
+            extended:false,
+            extensions:0,
+            state:ProposalState::Draft,
+            transitions:vec![(ProposalState::Draft,created_at)],
+        }
+    }
+
     pub fn end_time(&self)->DateTime<Utc>{
         self.vote_start+self.voting_window.duration()
     }
@@ -36,6 +122,11 @@ impl VotingSession{
         now>self.end_time()
     }
 
+    //the window and the grace period after it have both passed
+    pub fn past_grace(&self,grace:Duration,now:DateTime<Utc>)->bool{
+        self.has_expired(now-grace)
+    }
+
     pub fn remaining_time(&self,now:DateTime<Utc>)->Duration{
         let end=self.end_time();
         if now>=end{
@@ -45,48 +136,244 @@ impl VotingSession{
             end-now
         }
     }
-    pub fn extend_if_possible(&mut self, extension: Duration) {
+
+    pub fn extend_if_possible(&mut self, extension: Duration, now: DateTime<Utc>) {
         if !self.extended {
-            let current_duration = self.voting_window.duration();
-            self.voting_window = VotingWindow::Custom(current_duration + extension);
-            self.extended = true;
+            let _ = self.extend(extension, now);
+        }
+    }
+
+    pub fn extend(&mut self,extension:Duration,now:DateTime<Utc>)->Result<()>{
+        self.transition(ProposalState::Extended,now)?;
+        let current_duration=self.voting_window.duration();
+        self.voting_window=VotingWindow::Custom(current_duration+extension);
+        self.extended=true;
+        self.extensions+=1;
+        Ok(())
+    }
+
+    pub fn transition(&mut self,next:ProposalState,now:DateTime<Utc>)->Result<()>{
+        if !self.state.can_transition_to(next){
+            return Err(Error::InvalidTransition{from:self.state,to:next});
+        }
+        if self.state==ProposalState::Draft && next==ProposalState::Open{
+            self.vote_start=now;
+        }
+        self.state=next;
+        self.transitions.push((next,now));
+        Ok(())
+    }
+
+    //when the session entered the given state, if it ever did
+    pub fn entered_at(&self,state:ProposalState)->Option<DateTime<Utc>>{
+        self.transitions
+        .iter()
+        .rev()
+        .find(|(s,_)| *s==state)
+        .map(|(_,at)| *at)
+    }
+}
+
+// Extends a window that is about to close while the tally sits just below the threshold.
+#[derive(Debug,Clone)]
+pub struct ExtensionPolicy{
+    pub margin_band:Decimal,      //extend when threshold - yes_share is within this band, and still short
+    pub final_stretch:Duration,   //only look at proposals with this much time left (or less)
+    pub extension:Duration,
+    pub max_extensions:u32,
+    pub hard_deadline:Duration,   //no window may run past vote_start + hard_deadline
+}
+
+impl Default for ExtensionPolicy{
+    fn default()->Self{
+        Self{
+            margin_band:dec!(0.05),
+            final_stretch:Duration::minutes(2),
+            extension:Duration::minutes(5),
+            max_extensions:3,
+            hard_deadline:Duration::hours(4),
+        }
+    }
+}
+
+impl ExtensionPolicy{
+    //how long to extend the session by, if at all
+    pub fn extension_for(&self,session:&VotingSession,outcome:&TallyOutcome,now:DateTime<Utc>)->Option<Duration>{
+        if !session.state.is_voting()
+            || outcome.status!=TallyStatus::Pending
+            || session.extensions>=self.max_extensions
+            || session.remaining_time(now)>self.final_stretch
+            || outcome.margin< -self.margin_band
+            || outcome.margin>=Decimal::ZERO
+        {
+            return None;
+        }
+        let deadline=session.vote_start+self.hard_deadline;
+        let extension=self.extension.min(deadline-session.end_time());
+        if extension<=Duration::zero(){
+            return None;
         }
+        Some(extension)
     }
 }
 
 pub struct ProposalManager{
     pub proposals:HashMap<String,VotingSession>,
     pub grace_period:Duration,
+    pub retention:Duration, //how long finished proposals are kept after reaching a terminal state
+    pub extension_policy:Option<ExtensionPolicy>,
+    pub clock:Arc<dyn Clock>,
 }
 
 impl ProposalManager{
     pub fn new(grace_period_secs:i64)->Self{
+        Self::with_clock(grace_period_secs,system_clock())
+    }
+
+    pub fn with_clock(grace_period_secs:i64,clock:Arc<dyn Clock>)->Self{
         Self{
             proposals:HashMap::new(),
             grace_period:Duration::seconds(grace_period_secs),
+            retention:Duration::days(30),
+            extension_policy:None,
+            clock,
         }
     }
 
+    pub fn now(&self)->DateTime<Utc>{
+        self.clock.now()
+    }
+
     pub fn add_proposal(&mut self, proposal_id: String, voter_id: String, voting_window: VotingWindow) {
-        let session=VotingSession{
-            vote_start: Utc::now(),
-            voter_id: voter_id.to_string(),
-            voting_window,
-            extended: false,
+        let session=VotingSession::new(self.clock.now(),voter_id,voting_window);
+        self.proposals.insert(proposal_id,session);
+    }
+
+    //opens a proposal under a threshold policy and returns its tally; the policy
+    //and decay model are validated before anything is registered
+    pub fn create_proposal(
+        &mut self,
+        proposal_id:String,
+        voter_id:String,
+        voting_window:VotingWindow,
+        policy:ThresholdPolicy,
+        decay_model:&DecayModel,
+    )->Result<Tally>{
+        decay_model.validate()?;
+        let session=VotingSession::new(self.clock.now(),voter_id,voting_window);
+        let tally=Tally::with_policy(session.vote_start,session.end_time(),policy)?;
+        self.proposals.insert(proposal_id,session);
+        Ok(tally)
+    }
+
+    //registers a proposal that does not accept votes until opened
+    pub fn add_draft(&mut self,proposal_id:String,voter_id:String,voting_window:VotingWindow,now:DateTime<Utc>){
+        let session=VotingSession::draft(now,voter_id,voting_window);
+        self.proposals.insert(proposal_id,session);
+    }
+
+    pub fn session(&self,proposal_id:&str)->Result<&VotingSession>{
+        self.proposals
+        .get(proposal_id)
+        .ok_or_else(|| Error::UnknownProposal(proposal_id.to_string()))
+    }
+
+    pub fn transition(&mut self,proposal_id:&str,next:ProposalState,now:DateTime<Utc>)->Result<()>{
+        self.proposals
+        .get_mut(proposal_id)
+        .ok_or_else(|| Error::UnknownProposal(proposal_id.to_string()))?
+        .transition(next,now)
+    }
+
+    //rejects votes for proposals that are not open, or whose window (plus grace) has passed
+    pub fn check_vote(&self,proposal_id:&str,vote_time:DateTime<Utc>)->Result<()>{
+        let session=self.session(proposal_id)?;
+        if !session.state.is_voting()
+            || vote_time<session.vote_start
+            || session.past_grace(self.grace_period,vote_time)
+        {
+            return Err(Error::VoteRejected{
+                proposal_id:proposal_id.to_string(),
+                state:session.state,
+            });
+        }
+        Ok(())
+    }
+
+    //closes voting and records the tally's decision; a failed tally with no
+    //decisive weight at all expires instead of being rejected
+    pub fn finalize(&mut self,proposal_id:&str,outcome:&TallyOutcome,now:DateTime<Utc>)->Result<ProposalState>{
+        let session=self.proposals
+        .get_mut(proposal_id)
+        .ok_or_else(|| Error::UnknownProposal(proposal_id.to_string()))?;
+
+        let decision=match outcome.status{
+            TallyStatus::Passed=>ProposalState::Passed,
+            TallyStatus::Failed if (outcome.yes_weight+outcome.no_weight).is_zero()=>ProposalState::Expired,
+            TallyStatus::Failed=>ProposalState::Rejected,
+            TallyStatus::Pending=>return Err(Error::Undecided(proposal_id.to_string())),
         };
-        self.proposals.insert(proposal_id.to_string(),session);
+        if session.state.is_voting(){
+            session.transition(ProposalState::Closed,now)?;
+        }
+        session.transition(decision,now)?;
+        Ok(decision)
+    }
+
+    //evaluates the live tally and applies the extension policy; the tally's
+    //end time follows the session so pending/failed stays accurate
+    pub fn tick(&mut self,proposal_id:&str,tally:&mut Tally,now:DateTime<Utc>)->Result<TallyOutcome>{
+        let session=self.proposals
+        .get_mut(proposal_id)
+        .ok_or_else(|| Error::UnknownProposal(proposal_id.to_string()))?;
+
+        tally.vote_end=session.end_time();
+        let outcome=tally.evaluate(now);
+        let extension=self.extension_policy
+        .as_ref()
+        .and_then(|policy| policy.extension_for(session,&outcome,now));
+
+        match extension{
+            Some(extension)=>{
+                session.extend(extension,now)?;
+                tally.vote_end=session.end_time();
+                Ok(tally.evaluate(now))
+            }
+            None=>Ok(outcome),
+        }
     }
 
     pub fn list_actives(&self,now:DateTime<Utc>)->Vec<(&String,&VotingSession)>{
         self.proposals
         .iter()
-        .filter(|(_,session)| !session.has_expired(now+self.grace_period))
+        .filter(|(_,session)| session.state.is_voting()&&!session.past_grace(self.grace_period,now))
         .collect()
     }
 
+    pub fn list_in_state(&self,state:ProposalState)->Vec<(&String,&VotingSession)>{
+        self.proposals
+        .iter()
+        .filter(|(_,session)| session.state==state)
+        .collect()
+    }
+
+    //voting sessions whose window (plus grace) has passed move to Expired and are
+    //kept; only terminal sessions older than the retention period are dropped
     pub fn cleanup_expired(&mut self,now:DateTime<Utc>){
+        for session in self.proposals.values_mut(){
+            if session.state.is_voting() && session.past_grace(self.grace_period,now){
+                let closed_at=session.end_time()+self.grace_period;
+                //Open/Extended -> Closed -> Expired is always allowed
+                session.transition(ProposalState::Closed,closed_at).expect("voting session can close");
+                session.transition(ProposalState::Expired,now).expect("closed session can expire");
+            }
+        }
+        let retention=self.retention;
         self.proposals
-        .retain(|_,session| !session.has_expired(now+self.grace_period));
+        .retain(|_,session| {
+            !session.state.is_terminal()
+                || session.transitions.last().is_none_or(|(_,at)| now-*at<=retention)
+        });
     }
 }
 
@@ -110,12 +397,7 @@ mod tests {
     #[test]
     fn test_session_end_time_and_expiration() {
         let start_time = Utc::now();
-        let session = VotingSession {
-            vote_start: start_time,
-            voter_id: "voter1".to_string(),
-            voting_window: VotingWindow::Short,
-            extended: false,
-        };
+        let session = VotingSession::new(start_time, "voter1".to_string(), VotingWindow::Short);
 
         let expected_end = start_time + Duration::minutes(5);
         assert_eq!(session.end_time(), expected_end);
@@ -129,19 +411,15 @@ mod tests {
 
     #[test]
     fn test_remaining_time_and_extension() {
-        let mut session = VotingSession {
-            vote_start: Utc::now(),
-            voter_id: "voter1".to_string(),
-            voting_window: VotingWindow::Short,
-            extended: false,
-        };
+        let mut session = VotingSession::new(Utc::now(), "voter1".to_string(), VotingWindow::Short);
 
         let now = session.vote_start + Duration::minutes(2);
         let remaining = session.remaining_time(now);
         assert_eq!(remaining, Duration::minutes(3));
 
-        session.extend_if_possible(Duration::minutes(5));
+        session.extend_if_possible(Duration::minutes(5), now);
         assert!(session.extended);
+        assert_eq!(session.state, ProposalState::Extended);
 
         let new_end = session.vote_start + Duration::minutes(10); // 5 original + 5 extension
         assert_eq!(session.end_time(), new_end);
@@ -158,18 +436,57 @@ mod tests {
         assert_eq!(actives[0].0, &"p1".to_string());
     }
 
+    #[test]
+    fn test_list_actives_skips_finished_and_applies_grace_after_the_end() {
+        let mut manager = ProposalManager::new(60);
+        manager.add_proposal("open".to_string(), "voterA".to_string(), VotingWindow::Short);
+        manager.add_proposal("cancelled".to_string(), "voterB".to_string(), VotingWindow::Short);
+        let start = manager.session("open").unwrap().vote_start;
+        manager.transition("cancelled", ProposalState::Cancelled, start).unwrap();
+
+        let actives = manager.list_actives(start);
+        assert_eq!(actives.len(), 1);
+        assert_eq!(actives[0].0, &"open".to_string());
+
+        // grace runs after the end, as for check_vote and cleanup_expired
+        let end = manager.session("open").unwrap().end_time();
+        let in_grace = end + Duration::seconds(30);
+        assert!(manager.check_vote("open", in_grace).is_ok());
+        assert_eq!(manager.list_actives(in_grace).len(), 1);
+        let after_grace = end + Duration::seconds(61);
+        assert!(manager.check_vote("open", after_grace).is_err());
+        assert!(manager.list_actives(after_grace).is_empty());
+        assert_eq!(manager.list_actives(end - Duration::seconds(1)).len(), 1);
+    }
+
+    #[test]
+    fn test_create_proposal_validates_policy() {
+        use crate::threshold::{ThresholdFault, ThresholdModel};
+
+        let mut manager = ProposalManager::new(60);
+        let create = |manager: &mut ProposalManager, policy: ThresholdPolicy, decay: DecayModel| {
+            manager.create_proposal("p1".to_string(), "voterA".to_string(), VotingWindow::Short, policy, &decay)
+        };
+        let decreasing = ThresholdPolicy::new(ThresholdModel::StepFn(vec![(0, 0.8), (5, 0.6)]));
+        assert_eq!(
+            create(&mut manager, decreasing, DecayModel::Linear(0.0)).err(),
+            Some(Error::InvalidThreshold(ThresholdFault::DecreasingStep { index: 1 }))
+        );
+        let sound = ThresholdPolicy::new(ThresholdModel::Linear(0.01));
+        assert!(matches!(create(&mut manager, sound.clone(), DecayModel::Linear(-1.0)), Err(Error::InvalidDecay(_))));
+        assert!(manager.session("p1").is_err());
+
+        let tally = create(&mut manager, sound, DecayModel::Linear(0.0)).unwrap();
+        assert_eq!(tally.vote_end, manager.session("p1").unwrap().end_time());
+    }
+
     #[test]
     fn test_proposal_cleanup_expired() {
         let mut manager = ProposalManager::new(0); // No grace period
 
         // Add an expired proposal manually
         let past_time = Utc::now() - Duration::minutes(10);
-        let expired_session = VotingSession {
-            vote_start: past_time,
-            voter_id: "voterX".to_string(),
-            voting_window: VotingWindow::Short, // expired
-            extended: false,
-        };
+        let expired_session = VotingSession::new(past_time, "voterX".to_string(), VotingWindow::Short); // expired
 
         manager.proposals.insert("expired".to_string(), expired_session);
 
@@ -178,8 +495,256 @@ mod tests {
 
         manager.cleanup_expired(Utc::now());
 
-        assert_eq!(manager.proposals.len(), 1);
-        assert!(manager.proposals.contains_key("active"));
+        // the lapsed session is expired but kept for the retention period
+        assert_eq!(manager.proposals.len(), 2);
+        let expired = manager.session("expired").unwrap();
+        assert_eq!(expired.state, ProposalState::Expired);
+        assert!(expired.entered_at(ProposalState::Closed).is_some());
+        assert_eq!(manager.session("active").unwrap().state, ProposalState::Open);
+
+        manager.cleanup_expired(Utc::now() + manager.retention + Duration::minutes(1));
         assert!(!manager.proposals.contains_key("expired"));
+        // no longer voting, so it expired as well but is still retained
+        assert_eq!(manager.session("active").unwrap().state, ProposalState::Expired);
+    }
+
+    #[test]
+    fn test_session_lookup() {
+        let mut manager = ProposalManager::new(60);
+        manager.add_proposal("p1".to_string(), "voterA".to_string(), VotingWindow::Short);
+
+        assert!(manager.session("p1").is_ok());
+        assert_eq!(
+            manager.session("missing").unwrap_err(),
+            Error::UnknownProposal("missing".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lifecycle_transitions_are_validated() {
+        let created = Utc::now();
+        let mut session = VotingSession::draft(created, "voterA".to_string(), VotingWindow::Short);
+        assert_eq!(session.state, ProposalState::Draft);
+
+        assert_eq!(
+            session.transition(ProposalState::Closed, created),
+            Err(Error::InvalidTransition {
+                from: ProposalState::Draft,
+                to: ProposalState::Closed,
+            })
+        );
+
+        let opened = created + Duration::minutes(1);
+        session.transition(ProposalState::Open, opened).unwrap();
+        assert_eq!(session.vote_start, opened);
+
+        let closed = opened + Duration::minutes(5);
+        session.transition(ProposalState::Closed, closed).unwrap();
+        session.transition(ProposalState::Rejected, closed).unwrap();
+        assert!(session.state.is_terminal());
+        assert!(session.transition(ProposalState::Executed, closed).is_err());
+
+        assert_eq!(session.entered_at(ProposalState::Draft), Some(created));
+        assert_eq!(session.entered_at(ProposalState::Open), Some(opened));
+        assert_eq!(session.entered_at(ProposalState::Closed), Some(closed));
+        assert_eq!(session.transitions.len(), 4);
+    }
+
+    #[test]
+    fn test_check_vote_rejects_wrong_state() {
+        let now = Utc::now();
+        let mut manager = ProposalManager::new(0);
+        manager.add_draft("p1".to_string(), "voterA".to_string(), VotingWindow::Short, now);
+
+        assert_eq!(
+            manager.check_vote("p1", now),
+            Err(Error::VoteRejected {
+                proposal_id: "p1".to_string(),
+                state: ProposalState::Draft,
+            })
+        );
+
+        manager.transition("p1", ProposalState::Open, now).unwrap();
+        assert!(manager.check_vote("p1", now + Duration::minutes(1)).is_ok());
+        assert!(manager.check_vote("p1", now + Duration::minutes(6)).is_err());
+
+        manager.transition("p1", ProposalState::Cancelled, now).unwrap();
+        assert!(manager.check_vote("p1", now + Duration::minutes(1)).is_err());
+        assert!(manager.check_vote("missing", now).is_err());
+    }
+
+    #[test]
+    fn test_finalize_records_decision() {
+        use crate::decay::DecayModel;
+        use crate::tally::Tally;
+        use crate::threshold::ThresholdModel;
+        use crate::voter::Choice;
+        use crate::weight::WeightedVote;
+        use rust_decimal_macros::dec;
+
+        let now = Utc::now();
+        let mut manager = ProposalManager::new(0);
+        manager.add_draft("p1".to_string(), "voterA".to_string(), VotingWindow::Short, now);
+        manager.transition("p1", ProposalState::Open, now).unwrap();
+        let end = manager.session("p1").unwrap().end_time();
+
+        let mut tally = Tally::new(now, end, ThresholdModel::Linear(0.01), None).unwrap();
+        let pending = tally.evaluate(now);
+        assert_eq!(manager.finalize("p1", &pending, now), Err(Error::Undecided("p1".to_string())));
+
+        tally.add_vote(
+            Choice::Yes,
+            WeightedVote {
+                voter_id: "alice".to_string(),
+                vote_time: now,
+                orig_weight: dec!(1.0),
+                decay_model: DecayModel::Linear(0.0),
+                reputation_bonus: dec!(0.0),
+            },
+        );
+        // still undecided until the window closes
+        assert!(manager.finalize("p1", &tally.evaluate(now), now).is_err());
+        let state = manager.finalize("p1", &tally.evaluate(end), end).unwrap();
+        assert_eq!(state, ProposalState::Passed);
+        assert_eq!(manager.list_in_state(ProposalState::Passed).len(), 1);
+
+        manager.transition("p1", ProposalState::Executed, end).unwrap();
+        assert_eq!(manager.session("p1").unwrap().entered_at(ProposalState::Executed), Some(end));
+    }
+
+    #[test]
+    fn test_finalize_without_votes_expires() {
+        let now = Utc::now();
+        let mut manager = ProposalManager::new(0);
+        manager.add_proposal("p1".to_string(), "voterA".to_string(), VotingWindow::Short);
+        let end = manager.session("p1").unwrap().end_time();
+
+        let tally = crate::tally::Tally::new(now, end, crate::threshold::ThresholdModel::Linear(0.01), None).unwrap();
+        let state = manager.finalize("p1", &tally.evaluate(end), end).unwrap();
+        assert_eq!(state, ProposalState::Expired);
+    }
+
+    fn close_race_tally(start: DateTime<Utc>, end: DateTime<Utc>) -> crate::tally::Tally {
+        use crate::decay::DecayModel;
+        use crate::threshold::ThresholdModel;
+        use crate::voter::Choice;
+        use crate::weight::WeightedVote;
+
+        // 53 yes / 47 no against a flat 0.55 threshold: 2% short
+        let mut tally = crate::tally::Tally::new(start, end, ThresholdModel::StepFn(vec![(0, 0.55)]), None).unwrap();
+        for (choice, weight) in [(Choice::Yes, dec!(53)), (Choice::No, dec!(47))] {
+            tally.add_vote(
+                choice,
+                WeightedVote {
+                    voter_id: format!("{:?}", choice),
+                    vote_time: start,
+                    orig_weight: weight,
+                    decay_model: DecayModel::Linear(0.0),
+                    reputation_bonus: dec!(0.0),
+                },
+            );
+        }
+        tally
+    }
+
+    #[test]
+    fn test_tick_extends_close_race_in_final_stretch() {
+        let mut manager = ProposalManager::new(0);
+        manager.extension_policy = Some(ExtensionPolicy::default());
+        manager.add_proposal("p1".to_string(), "voterA".to_string(), VotingWindow::Short);
+        let start = manager.session("p1").unwrap().vote_start;
+        let mut tally = close_race_tally(start, start + Duration::minutes(5));
+
+        // too early in the window
+        manager.tick("p1", &mut tally, start + Duration::minutes(1)).unwrap();
+        assert_eq!(manager.session("p1").unwrap().extensions, 0);
+
+        let outcome = manager.tick("p1", &mut tally, start + Duration::minutes(4)).unwrap();
+        let session = manager.session("p1").unwrap();
+        assert_eq!(session.extensions, 1);
+        assert_eq!(session.state, ProposalState::Extended);
+        assert_eq!(session.end_time(), start + Duration::minutes(10));
+        assert_eq!(tally.vote_end, session.end_time());
+        assert_eq!(outcome.status, TallyStatus::Pending);
+    }
+
+    #[test]
+    fn test_tick_respects_max_extensions_and_hard_deadline() {
+        let mut manager = ProposalManager::new(0);
+        manager.extension_policy = Some(ExtensionPolicy {
+            max_extensions: 5,
+            hard_deadline: Duration::minutes(12),
+            ..ExtensionPolicy::default()
+        });
+        manager.add_proposal("p1".to_string(), "voterA".to_string(), VotingWindow::Short);
+        let start = manager.session("p1").unwrap().vote_start;
+        let mut tally = close_race_tally(start, start + Duration::minutes(5));
+
+        manager.tick("p1", &mut tally, start + Duration::minutes(4)).unwrap();
+        manager.tick("p1", &mut tally, start + Duration::minutes(9)).unwrap();
+        manager.tick("p1", &mut tally, start + Duration::minutes(11)).unwrap();
+
+        let session = manager.session("p1").unwrap();
+        assert_eq!(session.extensions, 2);
+        assert_eq!(session.end_time(), start + Duration::minutes(12));
+
+        let outcome = manager.tick("p1", &mut tally, start + Duration::minutes(12)).unwrap();
+        assert_eq!(outcome.status, TallyStatus::Failed);
+    }
+
+    #[test]
+    fn test_tick_ignores_wide_margin() {
+        let mut manager = ProposalManager::new(0);
+        manager.extension_policy = Some(ExtensionPolicy {
+            margin_band: dec!(0.01),
+            ..ExtensionPolicy::default()
+        });
+        manager.add_proposal("p1".to_string(), "voterA".to_string(), VotingWindow::Short);
+        let start = manager.session("p1").unwrap().vote_start;
+        let mut tally = close_race_tally(start, start + Duration::minutes(5));
+
+        manager.tick("p1", &mut tally, start + Duration::minutes(4)).unwrap();
+        assert_eq!(manager.session("p1").unwrap().extensions, 0);
+    }
+
+    #[test]
+    fn test_tick_does_not_extend_passing_tally() {
+        let mut manager = ProposalManager::new(0);
+        manager.extension_policy = Some(ExtensionPolicy::default());
+        manager.add_proposal("p1".to_string(), "voterA".to_string(), VotingWindow::Short);
+        let start = manager.session("p1").unwrap().vote_start;
+        let mut tally = close_race_tally(start, start + Duration::minutes(5));
+        // 53% clears a flat 0.51 threshold
+        tally.policy.base = crate::threshold::ThresholdModel::Linear(0.0);
+
+        let outcome = manager.tick("p1", &mut tally, start + Duration::minutes(4)).unwrap();
+        assert!(outcome.margin > Decimal::ZERO);
+        assert_eq!(manager.session("p1").unwrap().extensions, 0);
+        assert_eq!(manager.session("p1").unwrap().end_time(), start + Duration::minutes(5));
+    }
+
+    #[test]
+    fn test_two_hour_window_with_simulated_clock() {
+        use crate::clock::SimulatedClock;
+        use chrono::TimeZone;
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap();
+        let clock = Arc::new(SimulatedClock::new(start));
+        let mut manager = ProposalManager::with_clock(0, clock.clone());
+        manager.add_proposal("p1".to_string(), "voterA".to_string(), VotingWindow::Long);
+        assert_eq!(manager.session("p1").unwrap().vote_start, start);
+
+        clock.advance(Duration::minutes(119));
+        assert!(manager.check_vote("p1", manager.now()).is_ok());
+        assert_eq!(manager.list_actives(manager.now()).len(), 1);
+
+        clock.advance(Duration::minutes(2));
+        assert!(manager.check_vote("p1", manager.now()).is_err());
+        manager.cleanup_expired(manager.now());
+        assert_eq!(manager.session("p1").unwrap().state, ProposalState::Expired);
+
+        clock.advance(manager.retention + Duration::seconds(1));
+        manager.cleanup_expired(manager.now());
+        assert!(manager.proposals.is_empty());
     }
 }